@@ -0,0 +1,46 @@
+// Compares dispatching robots with scents kept in a dense array against a sparse map,
+// e.g.
+//
+//     cargo bench --bench scents
+
+use std::iter;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use martian_robots::generator::Generator;
+use martian_robots::mission::Mission;
+use martian_robots::robot::{Command, Robot};
+
+type Robots = (Robot, Vec<Command>);
+
+fn dispatch(c: &mut Criterion) {
+    let generator = Generator::new(12345);
+    let world = generator.world();
+    let robots: Vec<_> = generator.take(10_000).collect();
+
+    let mut group = c.benchmark_group("dispatch");
+    group.throughput(Throughput::Elements(robots.len() as u64));
+
+    group.bench_function("dense scents", |b| {
+        b.iter(|| {
+            let mut mission = Mission::in_world(world.clone(), iter::empty::<Robots>());
+            for (robot, commands) in &robots {
+                mission.dispatch(*robot, commands);
+            }
+        })
+    });
+    group.bench_function("sparse scents", |b| {
+        b.iter(|| {
+            let mut mission =
+                Mission::in_world(world.clone(), iter::empty::<Robots>()).with_sparse_scents();
+            for (robot, commands) in &robots {
+                mission.dispatch(*robot, commands);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+// Generates the header for the C API, when building with the capi feature. It only goes
+// into OUT_DIR: the copy in include/ is updated by running make header in tests/c.
+fn main() {
+    #[cfg(feature = "capi")]
+    header();
+}
+
+#[cfg(feature = "capi")]
+fn header() {
+    use cbindgen::{Config, EnumConfig, Language, RenameRule};
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let mut config = Config::default();
+    config.language = Language::C;
+    config.include_guard = Some("MARTIAN_ROBOTS_H".to_string());
+    config.autogen_warning =
+        Some("/* Generated by cbindgen from src/capi.rs, don't edit */".into());
+    config.usize_is_size_t = true;
+    config.enumeration = EnumConfig {
+        rename_variants: RenameRule::ScreamingSnakeCase,
+        prefix_with_name: true,
+        ..EnumConfig::default()
+    };
+
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/capi.rs", crate_dir))
+        .generate()
+        .expect("Can't generate the C header")
+        .write_to_file(format!("{}/martian_robots.h", out_dir));
+
+    println!("cargo:rerun-if-changed=src/capi.rs");
+}
@@ -0,0 +1,38 @@
+// Registers a custom command, J, which makes robots jump over the next cell, and runs
+// a plan using it from STDIN, e.g.
+//
+//     printf "5 3\n1 1 E\nJLJ\n" | cargo run --example custom_command
+
+use std::io;
+
+use martian_robots::commands::{CommandSet, CustomCommand};
+use martian_robots::parser::MissionPlan;
+use martian_robots::print;
+use martian_robots::robot::Robot;
+
+struct Jump;
+
+impl CustomCommand for Jump {
+    fn apply(&self, robot: Robot) -> Robot {
+        Robot {
+            position: robot.position + robot.facing + robot.facing,
+            facing: robot.facing,
+        }
+    }
+}
+
+fn main() {
+    let mut commands = CommandSet::new();
+    commands.register('J', Jump).unwrap();
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    match MissionPlan::read(&mut input) {
+        Ok(plan) => {
+            let mission = plan.with_commands(commands).mission();
+            print::outcomes(&mut io::stdout(), &mut io::stderr(), mission).unwrap()
+        }
+        Err(msg) => eprintln!("{}", msg),
+    }
+}
@@ -0,0 +1,91 @@
+#ifndef MARTIAN_ROBOTS_H
+#define MARTIAN_ROBOTS_H
+
+/* Generated by cbindgen from src/capi.rs, don't edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum MrResult {
+  MR_RESULT_SUCCESS = 0,
+  MR_RESULT_LOST,
+  MR_RESULT_COLLIDED,
+} MrResult;
+
+typedef enum MrStatus {
+  MR_STATUS_OK = 0,
+  MR_STATUS_NULL_POINTER,
+  MR_STATUS_INVALID_FACING,
+  MR_STATUS_INVALID_COMMAND,
+  MR_STATUS_OUT_OF_RANGE,
+} MrStatus;
+
+/**
+ * A mission on a rectangular grid, created by mr_mission_new and freed by mr_mission_free
+ */
+typedef struct MrMission MrMission;
+
+/**
+ * Where a robot ended up. The facing is a NUL-terminated abbreviation, like "N" or "SW".
+ */
+typedef struct MrOutcome {
+  int64_t x;
+  int64_t y;
+  char facing[3];
+  enum MrResult result;
+} MrOutcome;
+
+/**
+ * A cell a robot was lost from, and the heading it was lost on
+ */
+typedef struct MrScent {
+  int64_t x;
+  int64_t y;
+  char heading[3];
+} MrScent;
+
+/**
+ * Creates a mission on a grid from (0, 0) to the upper right corner, or returns NULL if
+ * the corner is out of range
+ */
+struct MrMission *mr_mission_new(int64_t upper_x, int64_t upper_y);
+
+/**
+ * Frees a mission. Passing NULL does nothing.
+ *
+ * # Safety
+ *
+ * The mission must have come from mr_mission_new, and not have been freed already.
+ */
+void mr_mission_free(struct MrMission *mission);
+
+/**
+ * Dispatches a robot starting at (x, y) facing a way like "N", with commands like
+ * "RFRFRFRF", writing where it ended up to the outcome. Nothing is dispatched unless
+ * MR_STATUS_OK is returned.
+ *
+ * # Safety
+ *
+ * The mission must be live, the strings NUL-terminated, and the outcome writable.
+ */
+enum MrStatus mr_mission_dispatch(struct MrMission *mission,
+                                  int64_t x,
+                                  int64_t y,
+                                  const char *facing,
+                                  const char *commands,
+                                  struct MrOutcome *outcome);
+
+/**
+ * Copies up to capacity of the scents left so far into an array, ordered by cell and
+ * heading, returning how many scents there are in all. Pass a capacity of 0 to count them.
+ *
+ * # Safety
+ *
+ * The mission must be live, and the array must have room for capacity scents.
+ */
+size_t mr_mission_scents(const struct MrMission *mission, struct MrScent *scents, size_t capacity);
+
+#endif /* MARTIAN_ROBOTS_H */
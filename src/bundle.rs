@@ -0,0 +1,140 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::generator::Settings;
+use crate::mission::Obstacles;
+use crate::print;
+
+pub const MANIFEST: &str = "manifest.json";
+pub const PLAN: &str = "plan.txt";
+pub const EXPECTED: &str = "expected.txt";
+
+// A generated test case, kept in a directory of its own with the plan for candidates to
+// run and the outcomes they should get
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    pub settings: Settings,
+    pub robots: usize,
+    pub obstacles: Option<Obstacles>,
+}
+
+impl Case {
+    pub fn plan(&self) -> Result<String, String> {
+        let generator = self.settings.generator()?;
+        let mut plan = Vec::new();
+        print::plan(&mut plan, &generator.world(), generator.take(self.robots)).unwrap();
+
+        Ok(String::from_utf8(plan).unwrap())
+    }
+
+    pub fn expected(&self) -> Result<String, String> {
+        let mission = self.settings.generator()?.mission();
+        let outcomes = match self.obstacles {
+            Some(obstacles) => mission.with_obstacles(obstacles),
+            None => mission,
+        };
+
+        let mut expected = Vec::new();
+        for outcome in outcomes.take(self.robots) {
+            print::outcome(&mut expected, &outcome).unwrap();
+        }
+
+        Ok(String::from_utf8(expected).unwrap())
+    }
+}
+
+// A set of test cases, described by a manifest at the top of their directory
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Bundle {
+    pub cases: Vec<Case>,
+}
+
+impl Bundle {
+    // Generates a number of cases alike but for their seeds, which count up from the
+    // seed in the settings
+    pub fn generate(
+        settings: &Settings,
+        cases: usize,
+        robots: usize,
+        obstacles: Option<Obstacles>,
+    ) -> Bundle {
+        let cases = (0..cases)
+            .map(|i| Case {
+                name: format!("case-{:03}", i + 1),
+                settings: Settings {
+                    seed: settings.seed.wrapping_add(i as u64),
+                    ..settings.clone()
+                },
+                robots,
+                obstacles,
+            })
+            .collect();
+
+        Bundle { cases }
+    }
+
+    pub fn load(dir: &Path) -> Result<Bundle, String> {
+        let path = dir.join(MANIFEST);
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&json).map_err(|e| format!("Invalid manifest: {}", e))
+    }
+
+    // Writes the manifest, and the plan and expected outcomes for each case
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let failed = |e: io::Error| format!("Can't write {}: {}", dir.display(), e);
+
+        for case in &self.cases {
+            let (plan, expected) = (case.plan()?, case.expected()?);
+            let case_dir = dir.join(&case.name);
+            fs::create_dir_all(&case_dir).map_err(failed)?;
+            fs::write(case_dir.join(PLAN), plan).map_err(failed)?;
+            fs::write(case_dir.join(EXPECTED), expected).map_err(failed)?;
+        }
+
+        let manifest = serde_json::to_string_pretty(self).unwrap() + "\n";
+        fs::write(dir.join(MANIFEST), manifest).map_err(failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::parser::MissionPlan;
+
+    #[test]
+    fn counts_seeds_up() {
+        let bundle = Bundle::generate(&Settings::new(7), 3, 5, None);
+        let seeds: Vec<_> = bundle.cases.iter().map(|c| c.settings.seed).collect();
+
+        assert_eq!(seeds, vec![7, 8, 9]);
+        assert_eq!(bundle.cases[2].name, "case-003");
+    }
+
+    #[test]
+    fn expects_what_running_the_plan_gives() {
+        let case = &Bundle::generate(&Settings::new(12345), 1, 20, None).cases[0];
+
+        let plan = case.plan().unwrap();
+        let mut input = Cursor::new(&plan);
+        let (mut actual, mut errors) = (Vec::new(), Vec::new());
+        print::outcomes(
+            &mut actual,
+            &mut errors,
+            MissionPlan::read(&mut input).unwrap().mission(),
+        )
+        .unwrap();
+
+        assert!(errors.is_empty());
+        let expected = case.expected().unwrap();
+        assert_eq!(String::from_utf8(actual).unwrap(), expected);
+        assert_eq!(expected.lines().count(), 20);
+    }
+}
@@ -0,0 +1,254 @@
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::commands::CommandSet;
+use crate::geo::location::{Coord, Point};
+use crate::geo::orientation::Orientation;
+use crate::mission::{Mission, Outcome};
+use crate::robot::{Command, Robot};
+
+// A C API for embedding missions in other programs. The header is generated when building
+// with the capi feature, which is why the API is described in doc comments here: they're
+// copied into the header. The copy in include/martian_robots.h is kept in the repository,
+// and updated after changing the API with
+//
+//     cargo build --features capi && make -C tests/c header
+//
+// Coordinates are always 64 bit, whichever size the crate uses, so the API stays the same.
+
+type Fleet = Vec<(Robot, Vec<Command>)>;
+
+/// A mission on a rectangular grid, created by mr_mission_new and freed by mr_mission_free
+pub struct MrMission {
+    mission: Mission<Fleet, (Robot, Vec<Command>)>,
+}
+
+#[repr(C)]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum MrStatus {
+    Ok = 0,
+    NullPointer,
+    InvalidFacing,
+    InvalidCommand,
+    OutOfRange,
+}
+
+#[repr(C)]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum MrResult {
+    Success = 0,
+    Lost,
+    Collided,
+}
+
+/// Where a robot ended up. The facing is a NUL-terminated abbreviation, like "N" or "SW".
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MrOutcome {
+    pub x: i64,
+    pub y: i64,
+    pub facing: [c_char; 3],
+    pub result: MrResult,
+}
+
+/// A cell a robot was lost from, and the heading it was lost on
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MrScent {
+    pub x: i64,
+    pub y: i64,
+    pub heading: [c_char; 3],
+}
+
+#[allow(clippy::unnecessary_cast)] // Coord is already i64 with the wide feature
+fn wide(coord: Coord) -> i64 {
+    coord as i64
+}
+
+fn abbreviation(orientation: Orientation) -> [c_char; 3] {
+    let mut text = [0; 3];
+    for (c, byte) in text.iter_mut().zip(orientation.to_string().bytes()) {
+        *c = byte as c_char;
+    }
+    text
+}
+
+fn robot(x: i64, y: i64, facing: &CStr) -> Result<Robot, MrStatus> {
+    let x = Coord::try_from(x).map_err(|_| MrStatus::OutOfRange)?;
+    let y = Coord::try_from(y).map_err(|_| MrStatus::OutOfRange)?;
+    let facing = facing.to_str().map_err(|_| MrStatus::InvalidFacing)?;
+    let facing = facing.parse().map_err(|_| MrStatus::InvalidFacing)?;
+
+    Ok(Robot {
+        position: Point { x, y },
+        facing,
+    })
+}
+
+fn commands(text: &CStr) -> Result<Vec<Command>, MrStatus> {
+    let known = CommandSet::new();
+    text.to_bytes()
+        .iter()
+        .map(|&letter| {
+            known
+                .command(letter as char)
+                .ok_or(MrStatus::InvalidCommand)
+        })
+        .collect()
+}
+
+/// Creates a mission on a grid from (0, 0) to the upper right corner, or returns NULL if
+/// the corner is out of range
+#[no_mangle]
+pub extern "C" fn mr_mission_new(upper_x: i64, upper_y: i64) -> *mut MrMission {
+    match (Coord::try_from(upper_x), Coord::try_from(upper_y)) {
+        (Ok(x), Ok(y)) => Box::into_raw(Box::new(MrMission {
+            mission: Mission::new(Point { x, y }, Vec::new()),
+        })),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Frees a mission. Passing NULL does nothing.
+///
+/// # Safety
+///
+/// The mission must have come from mr_mission_new, and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn mr_mission_free(mission: *mut MrMission) {
+    if !mission.is_null() {
+        drop(Box::from_raw(mission));
+    }
+}
+
+/// Dispatches a robot starting at (x, y) facing a way like "N", with commands like
+/// "RFRFRFRF", writing where it ended up to the outcome. Nothing is dispatched unless
+/// MR_STATUS_OK is returned.
+///
+/// # Safety
+///
+/// The mission must be live, the strings NUL-terminated, and the outcome writable.
+#[no_mangle]
+pub unsafe extern "C" fn mr_mission_dispatch(
+    mission: *mut MrMission,
+    x: i64,
+    y: i64,
+    facing: *const c_char,
+    commands: *const c_char,
+    outcome: *mut MrOutcome,
+) -> MrStatus {
+    if mission.is_null() || facing.is_null() || commands.is_null() || outcome.is_null() {
+        return MrStatus::NullPointer;
+    }
+
+    let robot = match robot(x, y, CStr::from_ptr(facing)) {
+        Ok(robot) => robot,
+        Err(status) => return status,
+    };
+    let commands = match self::commands(CStr::from_ptr(commands)) {
+        Ok(commands) => commands,
+        Err(status) => return status,
+    };
+
+    let (robot, result) = match (*mission).mission.dispatch(robot, &commands) {
+        Outcome::Success(robot) => (robot, MrResult::Success),
+        Outcome::Lost(robot) => (robot, MrResult::Lost),
+        Outcome::Collided(robot) => (robot, MrResult::Collided),
+    };
+
+    *outcome = MrOutcome {
+        x: wide(robot.position.x),
+        y: wide(robot.position.y),
+        facing: abbreviation(robot.facing),
+        result,
+    };
+    MrStatus::Ok
+}
+
+/// Copies up to capacity of the scents left so far into an array, ordered by cell and
+/// heading, returning how many scents there are in all. Pass a capacity of 0 to count them.
+///
+/// # Safety
+///
+/// The mission must be live, and the array must have room for capacity scents.
+#[no_mangle]
+pub unsafe extern "C" fn mr_mission_scents(
+    mission: *const MrMission,
+    scents: *mut MrScent,
+    capacity: usize,
+) -> usize {
+    if mission.is_null() {
+        return 0;
+    }
+
+    let mut left = (*mission).mission.scents();
+    left.sort();
+
+    if !scents.is_null() {
+        for (i, (cell, heading)) in left.iter().take(capacity).enumerate() {
+            *scents.add(i) = MrScent {
+                x: wide(cell.x),
+                y: wide(cell.y),
+                heading: abbreviation(*heading),
+            };
+        }
+    }
+
+    left.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn dispatches_robots_through_the_c_api() {
+        let mission = mr_mission_new(5, 3);
+        let north = CString::new("N").unwrap();
+        let commands = CString::new("FRRFLLFFRRFLL").unwrap();
+        let mut outcome = MrOutcome {
+            x: 0,
+            y: 0,
+            facing: [0; 3],
+            result: MrResult::Success,
+        };
+
+        unsafe {
+            let status = mr_mission_dispatch(
+                mission,
+                3,
+                2,
+                north.as_ptr(),
+                commands.as_ptr(),
+                &mut outcome,
+            );
+            assert_eq!(status, MrStatus::Ok);
+            assert_eq!(
+                (outcome.x, outcome.y, outcome.result),
+                (3, 3, MrResult::Lost)
+            );
+
+            let bad = CString::new("Q").unwrap();
+            let status =
+                mr_mission_dispatch(mission, 3, 2, bad.as_ptr(), commands.as_ptr(), &mut outcome);
+            assert_eq!(status, MrStatus::InvalidFacing);
+
+            assert_eq!(mr_mission_scents(mission, ptr::null_mut(), 0), 1);
+            mr_mission_free(mission);
+        }
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/martian_robots.h"));
+        let kept = include_str!("../include/martian_robots.h");
+
+        assert!(
+            generated == kept,
+            "include/martian_robots.h is out of date, update it with make -C tests/c header"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use crate::generator::Generator;
+use crate::geo::world::World;
+use crate::mission::MissionState;
+
+// Where a mission gets its robots from, and how far into its input it got
+#[derive(Serialize, Deserialize)]
+pub enum Source {
+    // a plan for a world, with the next robot at an offset into it, in bytes
+    Plan { world: World, offset: u64 },
+    // a generator, with the outcomes being verified against it read up to an offset
+    Generator { generator: Generator, offset: u64 },
+}
+
+// A snapshot of a long running mission, which it can be resumed from if interrupted
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub robots: usize, // how many robots have been dispatched
+    pub mission: MissionState,
+    pub source: Source,
+}
+
+impl Checkpoint {
+    pub fn load(json: &str) -> Result<Checkpoint, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid checkpoint: {}", e))
+    }
+
+    pub fn save(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::location::Point;
+    use crate::geo::orientation::Orientation;
+    use crate::scents::Scent;
+
+    #[test]
+    fn resumes_generators() {
+        let mut generator = Generator::new(12345);
+        generator.nth(2);
+
+        let checkpoint = Checkpoint {
+            robots: 3,
+            mission: MissionState {
+                scents: vec![Scent {
+                    position: Point { x: 1, y: 2 },
+                    heading: Orientation::West,
+                }],
+                occupied: vec![],
+            },
+            source: Source::Generator {
+                generator,
+                offset: 42,
+            },
+        };
+
+        let loaded = Checkpoint::load(&checkpoint.save()).unwrap();
+        assert_eq!(loaded.robots, 3);
+        assert_eq!(loaded.mission, checkpoint.mission);
+
+        match (loaded.source, checkpoint.source) {
+            (
+                Source::Generator {
+                    generator: loaded, ..
+                },
+                Source::Generator { generator, .. },
+            ) => {
+                assert_eq!(
+                    loaded.take(5).collect::<Vec<_>>(),
+                    generator.take(5).collect::<Vec<_>>()
+                )
+            }
+            _ => panic!("Expected a generator"),
+        }
+    }
+}
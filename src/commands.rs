@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::geo::grid::Grid;
+use crate::geo::orientation::Orientation;
+use crate::robot::{Command, Robot};
+
+const BUILT_IN: &str = "LRFBUlr+-";
+
+// A command defined outside of this crate, available as Command::Custom(letter)
+// once registered in a CommandSet
+pub trait CustomCommand: Send + Sync {
+    // The state of the robot after executing the command
+    fn apply(&self, robot: Robot) -> Robot;
+
+    // Whether executing the command can lose the robot. Moves off the grid by commands
+    // which can't are skipped, and leave no scent.
+    fn can_lose(&self) -> bool {
+        true
+    }
+
+    // The direction a robot facing a given way travels in when executing the command,
+    // used to match scents
+    fn heading(&self, facing: Orientation) -> Orientation {
+        facing
+    }
+}
+
+// The commands understood by a mission, on top of the built-in ones
+#[derive(Clone, Default)]
+pub struct CommandSet {
+    custom: BTreeMap<char, Arc<dyn CustomCommand>>,
+}
+
+impl CommandSet {
+    pub fn new() -> CommandSet {
+        CommandSet::default()
+    }
+
+    // Adds a command under a new letter. Letters are upper case ASCII, and can't be
+    // one of the built-in commands or a command registered before.
+    pub fn register<C>(&mut self, letter: char, command: C) -> Result<(), String>
+    where
+        C: CustomCommand + 'static,
+    {
+        if !letter.is_ascii_uppercase() {
+            return Err(format!("Command '{}' is not an upper case letter", letter));
+        }
+
+        if BUILT_IN.contains(letter) || self.custom.contains_key(&letter) {
+            return Err(format!("Command '{}' is already defined", letter));
+        }
+
+        self.custom.insert(letter, Arc::new(command));
+        Ok(())
+    }
+
+    // All the command letters in the set, built-in ones first
+    pub fn letters(&self) -> Vec<u8> {
+        BUILT_IN
+            .chars()
+            .chain(self.custom.keys().copied())
+            .map(|letter| letter as u8)
+            .collect()
+    }
+
+    // The custom commands in the set, in order of their letters
+    pub fn custom(&self) -> Vec<Command> {
+        self.custom
+            .keys()
+            .map(|&letter| Command::Custom(letter))
+            .collect()
+    }
+
+    pub fn command(&self, letter: char) -> Option<Command> {
+        match letter {
+            'L' => Some(Command::Left),
+            'R' => Some(Command::Right),
+            'F' => Some(Command::Forward),
+            'B' => Some(Command::Backward),
+            'U' => Some(Command::UTurn),
+            'l' => Some(Command::HalfLeft),
+            'r' => Some(Command::HalfRight),
+            '+' => Some(Command::PitchUp),
+            '-' => Some(Command::PitchDown),
+            letter if self.custom.contains_key(&letter) => Some(Command::Custom(letter)),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, robot: Robot, command: Command, grid: Grid) -> Option<Robot> {
+        match command {
+            Command::Custom(letter) => match self.custom.get(&letter) {
+                Some(custom) => Some(custom.apply(robot)),
+                None => Some(robot),
+            },
+            command => robot.advance_on(grid, command),
+        }
+    }
+
+    pub fn can_lose(&self, command: Command) -> bool {
+        match command {
+            Command::Custom(letter) => self.custom.get(&letter).is_some_and(|c| c.can_lose()),
+            _ => true,
+        }
+    }
+
+    pub fn heading(&self, command: Command, facing: Orientation) -> Orientation {
+        match command {
+            Command::Custom(letter) => match self.custom.get(&letter) {
+                Some(custom) => custom.heading(facing),
+                None => facing,
+            },
+            command => command.heading(facing),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::location::Point;
+    use crate::geo::orientation::Orientation::{East, West};
+
+    struct Jump;
+
+    impl CustomCommand for Jump {
+        fn apply(&self, robot: Robot) -> Robot {
+            Robot {
+                position: robot.position + robot.facing + robot.facing,
+                facing: robot.facing,
+            }
+        }
+    }
+
+    #[test]
+    fn applies_custom_commands() {
+        let mut commands = CommandSet::new();
+        commands.register('J', Jump).unwrap();
+
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: East,
+        };
+        let actual = commands.apply(robot, Command::Custom('J'), Grid::Square);
+
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn applies_built_in_commands() {
+        let commands = CommandSet::new();
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: West,
+        };
+        let actual = commands.apply(robot, Command::UTurn, Grid::Square);
+
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn refuses_to_redefine_commands() {
+        let mut commands = CommandSet::new();
+
+        assert!(commands.register('J', Jump).is_ok());
+        assert!(commands.register('J', Jump).is_err());
+        assert!(commands.register('F', Jump).is_err());
+        assert!(commands.register('j', Jump).is_err());
+    }
+}
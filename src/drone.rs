@@ -0,0 +1,133 @@
+use crate::commands::CommandSet;
+use crate::geo::orientation::{Orientation, TurnDirection};
+use crate::geo::space::{Course, Pitch, Point3, Volume};
+use crate::robot::{Command, Rover};
+
+// A flying robot, which turns like a robot on a square grid and pitches up and down
+// to climb and descend
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Drone {
+    pub position: Point3,
+    pub facing: Orientation,
+    pub pitch: Pitch,
+}
+
+impl Drone {
+    // Where a drone ends up, or nothing if it would fly beyond the range of coordinates
+    pub fn advance(self, command: Command) -> Option<Drone> {
+        let turn = |direction| Drone {
+            facing: self.facing.turn(direction),
+            ..self
+        };
+
+        Some(match command {
+            Command::Left => turn(TurnDirection::Left),
+            Command::Right => turn(TurnDirection::Right),
+            Command::Forward | Command::Backward => Drone {
+                position: self.position.checked_add(self.course(command).as_point())?,
+                ..self
+            },
+            Command::UTurn => turn(TurnDirection::Around),
+            Command::HalfLeft => turn(TurnDirection::HalfLeft),
+            Command::HalfRight => turn(TurnDirection::HalfRight),
+            Command::PitchUp => Drone {
+                pitch: self.pitch.up(),
+                ..self
+            },
+            Command::PitchDown => Drone {
+                pitch: self.pitch.down(),
+                ..self
+            },
+            Command::Custom(_) => self,
+        })
+    }
+
+    // The direction the drone flies in when executing the command
+    pub fn course(&self, command: Command) -> Course {
+        let course = Course::new(self.facing, self.pitch);
+
+        match command {
+            Command::Backward => course.reverse(),
+            _ => course,
+        }
+    }
+}
+
+// Drones only understand the built-in commands
+impl Rover for Drone {
+    type Cell = Point3;
+    type Heading = Course;
+    type World = Volume;
+    type Edge = Course; // volumes are only ever left while flying on some course
+
+    fn cell(&self) -> Point3 {
+        self.position
+    }
+
+    fn within(&self, volume: &Volume) -> bool {
+        volume.contains(self.position)
+    }
+
+    fn execute(self, command: Command, _: &CommandSet, _: &Volume) -> Option<Drone> {
+        self.advance(command)
+    }
+
+    fn heading(&self, command: Command, _: &CommandSet) -> Course {
+        self.course(command)
+    }
+
+    fn edge(&self, command: Command, _: &CommandSet, _: &Volume) -> Course {
+        self.course(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::location::Coord;
+
+    fn drone(x: Coord, y: Coord, z: Coord, facing: Orientation, pitch: Pitch) -> Drone {
+        Drone {
+            position: Point3 { x, y, z },
+            facing,
+            pitch,
+        }
+    }
+
+    #[test]
+    fn drone_flies_level() {
+        let actual = drone(1, 1, 1, Orientation::East, Pitch::Level)
+            .advance(Command::Forward)
+            .unwrap();
+        let expected = drone(2, 1, 1, Orientation::East, Pitch::Level);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn drone_climbs_and_descends() {
+        let actual = [Command::PitchUp, Command::Forward, Command::Forward]
+            .iter()
+            .fold(drone(1, 1, 1, Orientation::East, Pitch::Level), |d, c| {
+                d.advance(*c).unwrap()
+            });
+        let expected = drone(1, 1, 3, Orientation::East, Pitch::Up);
+
+        assert_eq!(actual, expected);
+
+        let actual = actual.advance(Command::Backward).unwrap();
+        let expected = drone(1, 1, 2, Orientation::East, Pitch::Up);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn drone_turns_while_pitched() {
+        let actual = drone(1, 1, 1, Orientation::East, Pitch::Down)
+            .advance(Command::Left)
+            .unwrap();
+        let expected = drone(1, 1, 1, Orientation::North, Pitch::Down);
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,30 +1,229 @@
 use rand::distributions::Standard;
 use rand::prelude::*;
-use rand::rngs::SmallRng;
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
 
-use crate::geo::location::Point;
-use crate::geo::orientation::Orientation;
+use crate::commands::CommandSet;
+use crate::geo::grid::Grid;
+use crate::geo::location::{Coord, Point};
+use crate::geo::mask::Mask;
+use crate::geo::orientation::{Compass, Orientation};
+use crate::geo::world::{Shape, World};
 use crate::mission::Mission;
 use crate::robot::{Command, Robot};
 
+// Generators can be saved part way through, and pick up where they left off once loaded.
+// Custom commands aren't saved, and need adding again.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Generator {
+    pub lower_left: Point,
     pub upper_right: Point,
-    prng: SmallRng, // a pseudo random number generator
+    prng: Pcg64Mcg, // a pseudo random number generator
+    #[serde(skip)]
+    commands: CommandSet,
+    compass: Compass,
+    grid: Grid,
+    mask: Option<Mask>,
+    #[serde(default)]
+    hexagon: Option<Coord>, // the radius of a hexagonal world
 }
 
 impl Generator {
     pub fn new(seed: u64) -> Generator {
-        let mut prng = SmallRng::seed_from_u64(seed);
+        let mut prng = Pcg64Mcg::seed_from_u64(seed);
         let upper_right = Point {
-            x: prng.gen_range(1, 51),
-            y: prng.gen_range(1, 51),
+            x: prng.gen_range(1, 51) as Coord,
+            y: prng.gen_range(1, 51) as Coord,
         };
 
-        Generator { upper_right, prng }
+        Generator {
+            lower_left: World::ORIGIN,
+            upper_right,
+            prng,
+            commands: CommandSet::new(),
+            compass: Compass::Four,
+            grid: Grid::Square,
+            mask: None,
+            hexagon: None,
+        }
+    }
+
+    // Moves the world so its lower left corner is at the given point, keeping its size
+    pub fn with_lower_left(self, lower_left: Point) -> Generator {
+        let upper_right = Point {
+            x: lower_left.x + self.upper_right.x - self.lower_left.x,
+            y: lower_left.y + self.upper_right.y - self.lower_left.y,
+        };
+
+        Generator {
+            lower_left,
+            upper_right,
+            ..self
+        }
+    }
+
+    // Generates a world shaped like a random blob, covering about two thirds of the
+    // rectangle between the world's corners, so set the corners before this. Plans only
+    // describe masks on a square grid, so settings never ask for blobs on a hex one.
+    pub fn with_blob(mut self) -> Generator {
+        let world = self.world();
+        let (lower_left, upper_right) = (self.lower_left, self.upper_right);
+        let area = (upper_right.x - lower_left.x + 1) * (upper_right.y - lower_left.y + 1);
+
+        let centre = world.position(
+            (lower_left.x + upper_right.x).div_euclid(2),
+            (lower_left.y + upper_right.y).div_euclid(2),
+        );
+        let headings = match self.grid {
+            Grid::Square => Compass::Four.headings(),
+            Grid::Hex => Grid::Hex.headings(),
+        };
+        let mut mask = Mask::new();
+        let mut cells = vec![centre];
+        mask.insert(centre);
+
+        // grow the blob from a random cell it already covers, until it's big enough
+        while (mask.len() as Coord) < area * 2 / 3 {
+            let cell = cells[self.prng.gen_range(0, cells.len())];
+            let heading = headings[self.prng.gen_range(0, headings.len())];
+            let next = cell + self.grid.offset(heading);
+
+            if world.contains(next) && !mask.contains(next) {
+                mask.insert(next);
+                cells.push(next);
+            }
+        }
+
+        Generator {
+            mask: Some(mask),
+            ..self
+        }
+    }
+
+    // Generates a rectangular world on a hex grid, with robots using the six hex headings
+    pub fn with_grid(self, grid: Grid) -> Generator {
+        Generator { grid, ..self }
+    }
+
+    // Generates a hexagonal world on a hex grid, as wide as the shorter side of the
+    // rectangle between the world's corners. Hexagons are always centred on (radius,
+    // radius), so the corners move to cover it.
+    pub fn with_hexagon(self) -> Generator {
+        let (lower_left, upper_right) = (self.lower_left, self.upper_right);
+        let width = (upper_right.x - lower_left.x).min(upper_right.y - lower_left.y);
+        let radius = (width / 2).max(1);
+
+        Generator {
+            lower_left: World::ORIGIN,
+            upper_right: Point {
+                x: radius * 2,
+                y: radius * 2,
+            },
+            grid: Grid::Hex,
+            hexagon: Some(radius),
+            ..self
+        }
+    }
+
+    // Generates robots heading any way on the compass, turning half left and right as well
+    pub fn with_compass(self, compass: Compass) -> Generator {
+        Generator { compass, ..self }
+    }
+
+    // Generates custom commands as well as the built-in L, R and F
+    pub fn with_commands(self, commands: CommandSet) -> Generator {
+        Generator { commands, ..self }
+    }
+
+    pub fn world(&self) -> World {
+        let shape = match (&self.mask, self.hexagon) {
+            (Some(mask), _) => Shape::Mask(mask.clone()),
+            (None, Some(radius)) => Shape::Hexagon(radius),
+            (None, None) => Shape::Rectangle(self.lower_left, self.upper_right),
+        };
+
+        World {
+            grid: self.grid,
+            shape,
+        }
     }
 
     pub fn mission(self) -> Mission<Self, (Robot, Vec<Command>)> {
-        Mission::new(self.upper_right, self)
+        let commands = self.commands.clone();
+        Mission::in_world(self.world(), self).with_commands(commands)
+    }
+
+    // The commands robots are given, picked from at random
+    fn palette(&self) -> Vec<Command> {
+        let half_turns = match (self.grid, self.compass) {
+            (Grid::Square, Compass::Eight) => vec![Command::HalfLeft, Command::HalfRight],
+            _ => vec![],
+        };
+
+        [Command::Left, Command::Right, Command::Forward]
+            .iter()
+            .copied()
+            .chain(half_turns)
+            .chain(self.commands.custom())
+            .collect()
+    }
+}
+
+// Everything needed to make the same generator again, e.g. to record how a test case was
+// generated
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub seed: u64,
+    pub compass: Compass,
+    pub grid: Grid,
+    pub lower_left: Option<Point>,
+    pub blob: bool,
+    #[serde(default)]
+    pub hexagon: bool,
+}
+
+impl Settings {
+    pub fn new(seed: u64) -> Settings {
+        Settings {
+            seed,
+            compass: Compass::Four,
+            grid: Grid::Square,
+            lower_left: None,
+            blob: false,
+            hexagon: false,
+        }
+    }
+
+    // Refuses settings for worlds or robots plans can't describe, saying why
+    pub fn check(&self) -> Result<(), String> {
+        let hex = self.grid == Grid::Hex;
+        if hex && (self.compass == Compass::Eight || self.blob) {
+            return Err("Hex grids can't be eight way or blobs".to_string());
+        }
+        if self.hexagon && (!hex || self.lower_left.is_some()) {
+            return Err("Hexagons need a hex grid, and can't be moved".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn generator(&self) -> Result<Generator, String> {
+        self.check()?;
+        let generator = Generator::new(self.seed)
+            .with_compass(self.compass)
+            .with_grid(self.grid);
+
+        let generator = match self.lower_left {
+            Some(lower_left) => generator.with_lower_left(lower_left),
+            None => generator,
+        };
+
+        Ok(if self.blob {
+            generator.with_blob()
+        } else if self.hexagon {
+            generator.with_hexagon()
+        } else {
+            generator
+        })
     }
 }
 
@@ -32,23 +231,65 @@ impl Iterator for Generator {
     type Item = (Robot, Vec<Command>);
 
     fn next(&mut self) -> Option<Self::Item> {
+        let palette = self.palette();
         let rng = &mut self.prng;
 
-        let robot = Robot {
-            position: Point {
-                x: rng.gen_range(0, self.upper_right.x),
-                y: rng.gen_range(0, self.upper_right.y),
+        let (lower_left, upper_right) = (self.lower_left, self.upper_right);
+        let column = lower_left.x + below(rng, upper_right.x - lower_left.x);
+        let row = lower_left.y + below(rng, upper_right.y - lower_left.y);
+
+        let robot = match (self.grid, &self.mask, self.hexagon) {
+            (_, Some(mask), _) => {
+                let cells = mask.cells();
+                Robot {
+                    position: cells[rng.gen_range(0, cells.len())],
+                    facing: rng.sample(self.compass),
+                }
+            }
+            (_, None, Some(radius)) => {
+                // pick cells of the square around the hexagon until one is within it
+                let position = loop {
+                    let x = below(rng, radius * 2 + 1);
+                    let y = below(rng, radius * 2 + 1);
+                    if (x - y).abs() <= radius {
+                        break Point { x, y };
+                    }
+                };
+
+                Robot {
+                    position,
+                    facing: self.grid.headings()[rng.gen_range(0, 6)],
+                }
+            }
+            (Grid::Square, None, None) => Robot {
+                position: Point { x: column, y: row },
+                facing: rng.sample(self.compass),
+            },
+            (Grid::Hex, None, None) => Robot {
+                position: Point {
+                    x: column + row.div_euclid(2), // see World::contains
+                    y: row,
+                },
+                facing: self.grid.headings()[rng.gen_range(0, 6)],
             },
-            facing: rng.gen(),
         };
 
-        let ncmds = rng.gen_range(1, 100);
-        let commands = rng.sample_iter(Standard).take(ncmds).collect();
+        let ncmds: usize = rng.gen_range(1, 100);
+        let commands = (0..ncmds)
+            .map(|_| palette[rng.gen_range(0, palette.len() as i32) as usize])
+            .collect();
 
         Some((robot, commands))
     }
 }
 
+// Picks a number from zero up to a bound. Numbers are picked as 32-bit integers whatever
+// the width of coordinates, so a seed always generates the same robots.
+#[allow(clippy::unnecessary_cast)] // coordinates may be 32-bit already
+fn below<R: Rng>(rng: &mut R, bound: Coord) -> Coord {
+    rng.gen_range(0, bound as i32) as Coord
+}
+
 // Randomly generated custom types
 
 impl Distribution<Orientation> for Standard {
@@ -62,12 +303,11 @@ impl Distribution<Orientation> for Standard {
     }
 }
 
-impl Distribution<Command> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Command {
-        match rng.gen_range(0, 3) {
-            0 => Command::Left,
-            1 => Command::Right,
-            _ => Command::Forward,
+impl Distribution<Orientation> for Compass {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Orientation {
+        match self {
+            Compass::Four => rng.gen(),
+            Compass::Eight => self.headings()[rng.gen_range(0, 8)],
         }
     }
 }
@@ -88,4 +328,135 @@ mod tests {
         let generator = Generator::new(12345);
         assert_debug_snapshot!(generator.take(3).collect::<Vec<_>>());
     }
+
+    #[test]
+    fn generates_custom_commands() {
+        struct Wait;
+
+        impl crate::commands::CustomCommand for Wait {
+            fn apply(&self, robot: Robot) -> Robot {
+                robot
+            }
+        }
+
+        let mut commands = CommandSet::new();
+        commands.register('W', Wait).unwrap();
+
+        let generator = Generator::new(12345).with_commands(commands);
+        let generated = generator
+            .take(3)
+            .flat_map(|(_, cmds)| cmds)
+            .collect::<Vec<_>>();
+
+        assert!(generated.contains(&Command::Custom('W')));
+    }
+
+    #[test]
+    fn generates_eight_way_robots() {
+        let generator = Generator::new(12345).with_compass(Compass::Eight);
+        let robots = generator.take(20).collect::<Vec<_>>();
+
+        assert!(robots
+            .iter()
+            .any(|(robot, _)| robot.facing == Orientation::SouthWest));
+        assert!(robots
+            .iter()
+            .any(|(_, cmds)| cmds.contains(&Command::HalfRight)));
+    }
+
+    #[test]
+    fn generates_robots_below_the_origin() {
+        let generator = Generator::new(12345).with_lower_left(Point { x: -30, y: -30 });
+        let world = generator.world();
+        let robots = generator.take(20).collect::<Vec<_>>();
+
+        assert!(robots.iter().any(|(robot, _)| robot.position.x < 0));
+        for (robot, _) in robots {
+            assert!(world.contains(robot.position));
+        }
+    }
+
+    #[test]
+    fn generates_blobs() {
+        let generator = Generator::new(12345).with_blob();
+        let world = generator.world();
+        let (lower_left, upper_right) = (generator.lower_left, generator.upper_right);
+        let robots = generator.take(20).collect::<Vec<_>>();
+
+        match &world.shape {
+            Shape::Mask(mask) => {
+                let area = (upper_right.x - lower_left.x + 1) * (upper_right.y - lower_left.y + 1);
+                assert_eq!(mask.len() as Coord, area * 2 / 3);
+            }
+            shape => panic!("Expected a mask, got {:?}", shape),
+        }
+
+        for (robot, _) in robots {
+            assert!(world.contains(robot.position));
+        }
+    }
+
+    #[test]
+    fn refuses_settings_plans_cannot_describe() {
+        let hex = Settings {
+            grid: Grid::Hex,
+            ..Settings::new(12345)
+        };
+        let hex_blob = Settings {
+            blob: true,
+            ..hex.clone()
+        };
+        let square_hexagon = Settings {
+            hexagon: true,
+            ..Settings::new(12345)
+        };
+
+        assert!(hex.generator().is_ok());
+        assert!(hex_blob.generator().is_err());
+        assert!(square_hexagon.generator().is_err());
+    }
+
+    #[test]
+    fn grows_blobs_within_hex_worlds() {
+        // the middle of the corners is off this world, as hex rows lean right going north
+        let generator = Generator::new(12345)
+            .with_grid(Grid::Hex)
+            .with_lower_left(Point { x: 0, y: 0 });
+        let generator = Generator {
+            upper_right: Point { x: 1, y: 50 },
+            ..generator
+        }
+        .with_blob();
+
+        match &generator.world().shape {
+            Shape::Mask(mask) => assert_eq!(mask.len(), 2 * 51 * 2 / 3),
+            shape => panic!("Expected a mask, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn generates_hexagonal_worlds() {
+        let generator = Generator::new(12345).with_hexagon();
+        let world = generator.world();
+        let robots = generator.take(50).collect::<Vec<_>>();
+
+        assert_eq!(world.grid, Grid::Hex);
+        assert!(matches!(world.shape, Shape::Hexagon(radius) if radius >= 1));
+        for (robot, _) in robots {
+            assert!(world.contains(robot.position));
+            assert!(Grid::Hex.headings().contains(&robot.facing));
+        }
+    }
+
+    #[test]
+    fn generates_hex_robots_within_the_world() {
+        let generator = Generator::new(12345).with_grid(Grid::Hex);
+        let world = generator.world();
+        let robots = generator.take(20).collect::<Vec<_>>();
+
+        for (robot, _) in robots {
+            assert!(world.contains(robot.position));
+            assert!(Grid::Hex.headings().contains(&robot.facing));
+        }
+    }
 }
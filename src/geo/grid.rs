@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use super::location::Point;
+use super::orientation::{Orientation, TurnDirection};
+use Orientation::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
+
+// The layout of the cells robots move between.
+//
+// Hex grids are made of pointy-top hexagons in rows going north, using axial coordinates:
+// moving east or west keeps to a row, moving north east adds one to both coordinates and
+// moving north west only adds one to the row.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Grid {
+    Square,
+    Hex,
+}
+
+// Hex headings, clockwise in 60 degree steps
+const HEX_CLOCKWISE: [Orientation; 6] = [NorthEast, East, SouthEast, SouthWest, West, NorthWest];
+
+impl Grid {
+    // The headings robots can take on the grid
+    pub fn headings(&self) -> &'static [Orientation] {
+        match self {
+            Grid::Square => &[
+                North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest,
+            ],
+            Grid::Hex => &HEX_CLOCKWISE,
+        }
+    }
+
+    // The offset of the neighbouring cell in the direction of heading. Robots heading
+    // north or south on a hex grid have no neighbour to move to.
+    pub fn offset(&self, heading: Orientation) -> Point {
+        match self {
+            Grid::Square => heading.as_point(),
+            Grid::Hex => match heading {
+                East => Point { x: 1, y: 0 },
+                West => Point { x: -1, y: 0 },
+                NorthEast => Point { x: 1, y: 1 },
+                NorthWest => Point { x: 0, y: 1 },
+                SouthEast => Point { x: 0, y: -1 },
+                SouthWest => Point { x: -1, y: -1 },
+                North | South => Point { x: 0, y: 0 },
+            },
+        }
+    }
+
+    // Turns left and right are 90 degrees on a square grid and 60 degrees on a hex grid,
+    // where there are no half turns
+    pub fn turn(&self, heading: Orientation, direction: TurnDirection) -> Orientation {
+        match self {
+            Grid::Square => heading.turn(direction),
+            Grid::Hex => {
+                let steps = match direction {
+                    TurnDirection::Left => 5,
+                    TurnDirection::Right => 1,
+                    TurnDirection::Around => 3,
+                    TurnDirection::HalfLeft | TurnDirection::HalfRight => 0,
+                };
+
+                match HEX_CLOCKWISE.iter().position(|&h| h == heading) {
+                    Some(index) => HEX_CLOCKWISE[(index + steps) % 6],
+                    None => heading,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_on_a_hex_grid() {
+        let pairs = [
+            (NorthEast, NorthWest),
+            (NorthWest, West),
+            (West, SouthWest),
+            (SouthWest, SouthEast),
+            (SouthEast, East),
+            (East, NorthEast),
+        ];
+        for &(before, after) in &pairs {
+            assert_eq!(Grid::Hex.turn(before, TurnDirection::Left), after);
+            assert_eq!(Grid::Hex.turn(after, TurnDirection::Right), before);
+        }
+    }
+
+    #[test]
+    fn opposite_hex_headings_cancel_out() {
+        for &heading in Grid::Hex.headings() {
+            let back = Grid::Hex.turn(heading, TurnDirection::Around);
+            let actual = Grid::Hex.offset(heading) + Grid::Hex.offset(back);
+
+            assert_eq!(actual, Point { x: 0, y: 0 });
+        }
+    }
+}
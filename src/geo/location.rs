@@ -1,9 +1,27 @@
+use serde::{Deserialize, Serialize};
+
 use super::orientation::Orientation;
 
-#[derive(Eq, PartialEq, PartialOrd, Hash, Copy, Clone, Debug)]
+// Coordinates are 64 bits wide with the "wide" feature, for worlds billions of cells across
+#[cfg(not(feature = "wide"))]
+pub type Coord = i32;
+#[cfg(feature = "wide")]
+pub type Coord = i64;
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Point {
-    pub x: i32,
-    pub y: i32,
+    pub x: Coord,
+    pub y: Coord,
+}
+
+impl Point {
+    // The sum of two points, or nothing if a coordinate would be out of range
+    pub fn checked_add(self, other: Point) -> Option<Point> {
+        Some(Point {
+            x: self.x.checked_add(other.x)?,
+            y: self.y.checked_add(other.y)?,
+        })
+    }
 }
 
 impl std::ops::Add for Point {
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::location::{Coord, Point};
+
+// An arbitrary set of cells, for worlds which aren't a simple shape
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Mask {
+    cells: HashSet<Point>,
+}
+
+impl Mask {
+    // The most cells a mask read from a plan can cover, as each takes memory
+    pub const MAX_CELLS: u128 = 1 << 20;
+
+    pub fn new() -> Mask {
+        Mask::default()
+    }
+
+    // Reads cells from ASCII art, with '#' for a cell and anything else for a hole.
+    // Rows are listed top to bottom, and the bottom left character is the origin.
+    pub fn from_art<S: AsRef<str>>(rows: &[S]) -> Mask {
+        let height = rows.len() as Coord;
+        let cells = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| {
+                let y = height - 1 - r as Coord;
+                row.as_ref()
+                    .chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '#')
+                    .map(move |(x, _)| Point { x: x as Coord, y })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Mask { cells }
+    }
+
+    pub fn insert(&mut self, cell: Point) {
+        self.cells.insert(cell);
+    }
+
+    // Adds all the cells from the lower left to the upper right corner
+    pub fn insert_region(&mut self, lower_left: Point, upper_right: Point) {
+        for x in lower_left.x..=upper_right.x {
+            for y in lower_left.y..=upper_right.y {
+                self.cells.insert(Point { x, y });
+            }
+        }
+    }
+
+    // How many cells a region from the lower left to the upper right corner covers
+    pub fn region_len(lower_left: Point, upper_right: Point) -> u128 {
+        let side = |from: Coord, to: Coord| (to as i128 - from as i128 + 1).max(0) as u128;
+        side(lower_left.x, upper_right.x).saturating_mul(side(lower_left.y, upper_right.y))
+    }
+
+    pub fn contains(&self, cell: Point) -> bool {
+        self.cells.contains(&cell)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    // All the cells, ordered by row and then column
+    pub fn cells(&self) -> Vec<Point> {
+        let mut cells: Vec<Point> = self.cells.iter().copied().collect();
+        cells.sort_by_key(|p| (p.y, p.x));
+        cells
+    }
+
+    // The lower left and upper right corners of the smallest rectangle covering all cells
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let xs = self.cells.iter().map(|p| p.x);
+        let ys = self.cells.iter().map(|p| p.y);
+
+        Some((
+            Point {
+                x: xs.clone().min()?,
+                y: ys.clone().min()?,
+            },
+            Point {
+                x: xs.max()?,
+                y: ys.max()?,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_art_from_the_top() {
+        let mask = Mask::from_art(&["#..", "###"]);
+
+        assert!(mask.contains(Point { x: 0, y: 1 }));
+        assert!(!mask.contains(Point { x: 1, y: 1 }));
+        assert!(mask.contains(Point { x: 2, y: 0 }));
+        assert_eq!(mask.len(), 4);
+    }
+
+    #[test]
+    fn regions_overlap() {
+        let mut mask = Mask::new();
+        mask.insert_region(Point { x: 0, y: 0 }, Point { x: 2, y: 0 });
+        mask.insert_region(Point { x: 1, y: -1 }, Point { x: 1, y: 1 });
+
+        assert_eq!(mask.len(), 5);
+        assert_eq!(
+            mask.bounds(),
+            Some((Point { x: 0, y: -1 }, Point { x: 2, y: 1 }))
+        );
+    }
+}
@@ -1,2 +1,6 @@
+pub mod grid;
 pub mod location;
+pub mod mask;
 pub mod orientation;
+pub mod space;
+pub mod world;
@@ -1,13 +1,50 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
 use super::location::Point;
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Orientation {
+    #[serde(rename = "N")]
     North,
+    #[serde(rename = "E")]
     East,
+    #[serde(rename = "S")]
     South,
+    #[serde(rename = "W")]
     West,
+    #[serde(rename = "NE")]
+    NorthEast,
+    #[serde(rename = "SE")]
+    SouthEast,
+    #[serde(rename = "SW")]
+    SouthWest,
+    #[serde(rename = "NW")]
+    NorthWest,
+}
+use Orientation::{East, North, NorthEast, NorthWest, South, SouthEast, SouthWest, West};
+
+// The headings robots can take, either the four cardinal points, or all eight points
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Compass {
+    Four,
+    Eight,
+}
+
+impl Compass {
+    pub fn headings(&self) -> &'static [Orientation] {
+        match self {
+            Compass::Four => &[North, East, South, West],
+            Compass::Eight => &CLOCKWISE,
+        }
+    }
 }
-use Orientation::{East, North, South, West};
+
+// All headings, clockwise in 45 degree steps
+const CLOCKWISE: [Orientation; 8] = [
+    North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest,
+];
 
 impl Orientation {
     pub fn as_point(&self) -> Point {
@@ -16,6 +53,29 @@ impl Orientation {
             East => Point { x: 1, y: 0 },
             South => Point { x: 0, y: -1 },
             West => Point { x: -1, y: 0 },
+            NorthEast => Point { x: 1, y: 1 },
+            SouthEast => Point { x: 1, y: -1 },
+            SouthWest => Point { x: -1, y: -1 },
+            NorthWest => Point { x: -1, y: 1 },
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    // Reads an abbreviation, like N or SW, as plans write them
+    fn from_str(text: &str) -> Result<Orientation, String> {
+        match text {
+            "N" => Ok(North),
+            "E" => Ok(East),
+            "S" => Ok(South),
+            "W" => Ok(West),
+            "NE" => Ok(NorthEast),
+            "SE" => Ok(SouthEast),
+            "SW" => Ok(SouthWest),
+            "NW" => Ok(NorthWest),
+            _ => Err(format!("Unknown orientation '{}'", text)),
         }
     }
 }
@@ -23,25 +83,25 @@ impl Orientation {
 pub enum TurnDirection {
     Left,
     Right,
+    Around,
+    HalfLeft,
+    HalfRight,
 }
-use TurnDirection::{Left, Right};
+use TurnDirection::{Around, HalfLeft, HalfRight, Left, Right};
 
 impl Orientation {
     pub fn turn(&self, rhs: TurnDirection) -> Orientation {
-        match (self, rhs) {
-            (orientation, Left) => match orientation {
-                North => West,
-                West => South,
-                South => East,
-                East => North,
-            },
-            (orientation, Right) => match orientation {
-                North => East,
-                East => South,
-                South => West,
-                West => North,
-            },
-        }
+        // turns in 45 degree steps clockwise
+        let steps = match rhs {
+            Left => 6,
+            Right => 2,
+            Around => 4,
+            HalfLeft => 7,
+            HalfRight => 1,
+        };
+        let index = CLOCKWISE.iter().position(|o| o == self).unwrap();
+
+        CLOCKWISE[(index + steps) % 8]
     }
 }
 
@@ -65,4 +125,44 @@ mod tests {
             assert_eq!(before.turn(Left), after);
         }
     }
+
+    #[test]
+    fn turns_around() {
+        let pairs = [(North, South), (West, East), (South, North), (East, West)];
+        for &(before, after) in &pairs {
+            assert_eq!(before.turn(Around), after);
+        }
+    }
+
+    #[test]
+    fn turns_diagonals() {
+        let pairs = [
+            (NorthEast, NorthWest),
+            (NorthWest, SouthWest),
+            (SouthWest, SouthEast),
+            (SouthEast, NorthEast),
+        ];
+        for &(before, after) in &pairs {
+            assert_eq!(before.turn(Left), after);
+            assert_eq!(after.turn(Right), before);
+        }
+    }
+
+    #[test]
+    fn turns_half_left() {
+        let pairs = [
+            (North, NorthWest),
+            (NorthWest, West),
+            (West, SouthWest),
+            (SouthWest, South),
+            (South, SouthEast),
+            (SouthEast, East),
+            (East, NorthEast),
+            (NorthEast, North),
+        ];
+        for &(before, after) in &pairs {
+            assert_eq!(before.turn(HalfLeft), after);
+            assert_eq!(after.turn(HalfRight), before);
+        }
+    }
 }
@@ -0,0 +1,156 @@
+use super::location::Coord;
+use super::orientation::{Orientation, TurnDirection};
+
+#[derive(Eq, PartialEq, PartialOrd, Hash, Copy, Clone, Debug)]
+pub struct Point3 {
+    pub x: Coord,
+    pub y: Coord,
+    pub z: Coord, // altitude
+}
+
+impl Point3 {
+    // The sum of two points, or nothing if a coordinate would be out of range
+    pub fn checked_add(self, other: Point3) -> Option<Point3> {
+        Some(Point3 {
+            x: self.x.checked_add(other.x)?,
+            y: self.y.checked_add(other.y)?,
+            z: self.z.checked_add(other.z)?,
+        })
+    }
+}
+
+impl std::ops::Add for Point3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Point3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl std::ops::Add<Course> for Point3 {
+    type Output = Self;
+
+    fn add(self, other: Course) -> Self {
+        self + other.as_point()
+    }
+}
+
+// Which way a flying robot's nose points, relative to the horizon
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Pitch {
+    Level,
+    Up,
+    Down,
+}
+
+impl Pitch {
+    // Pitching further than straight up or down leaves the pitch as it is
+    pub fn up(self) -> Pitch {
+        match self {
+            Pitch::Down => Pitch::Level,
+            _ => Pitch::Up,
+        }
+    }
+
+    pub fn down(self) -> Pitch {
+        match self {
+            Pitch::Up => Pitch::Level,
+            _ => Pitch::Down,
+        }
+    }
+}
+
+// A direction of travel through a volume: level along one of the compass headings,
+// or straight up or down
+#[derive(PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone, Debug)]
+pub enum Course {
+    Level(Orientation),
+    Up,
+    Down,
+}
+
+impl Course {
+    pub fn new(facing: Orientation, pitch: Pitch) -> Course {
+        match pitch {
+            Pitch::Level => Course::Level(facing),
+            Pitch::Up => Course::Up,
+            Pitch::Down => Course::Down,
+        }
+    }
+
+    pub fn reverse(self) -> Course {
+        match self {
+            Course::Level(facing) => Course::Level(facing.turn(TurnDirection::Around)),
+            Course::Up => Course::Down,
+            Course::Down => Course::Up,
+        }
+    }
+
+    pub fn as_point(&self) -> Point3 {
+        match self {
+            Course::Level(facing) => {
+                let offset = facing.as_point();
+                Point3 {
+                    x: offset.x,
+                    y: offset.y,
+                    z: 0,
+                }
+            }
+            Course::Up => Point3 { x: 0, y: 0, z: 1 },
+            Course::Down => Point3 { x: 0, y: 0, z: -1 },
+        }
+    }
+}
+
+// A box of cells flying robots can move between without getting lost, from the origin
+// to the upper corner
+#[derive(PartialEq, Clone, Debug)]
+pub struct Volume {
+    pub upper: Point3,
+}
+
+impl Volume {
+    pub fn contains(&self, position: Point3) -> bool {
+        (0..=self.upper.x).contains(&position.x)
+            && (0..=self.upper.y).contains(&position.y)
+            && (0..=self.upper.z).contains(&position.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitches_no_further_than_vertical() {
+        assert_eq!(Pitch::Level.up(), Pitch::Up);
+        assert_eq!(Pitch::Up.up(), Pitch::Up);
+        assert_eq!(Pitch::Up.down().down(), Pitch::Down);
+        assert_eq!(Pitch::Down.down(), Pitch::Down);
+    }
+
+    #[test]
+    fn reverses_courses() {
+        let point = Point3 { x: 1, y: 1, z: 1 };
+
+        for &course in &[Course::Level(Orientation::NorthEast), Course::Up] {
+            assert_eq!(point + course + course.reverse(), point);
+        }
+    }
+
+    #[test]
+    fn volumes_include_their_corners() {
+        let volume = Volume {
+            upper: Point3 { x: 2, y: 3, z: 1 },
+        };
+
+        assert!(volume.contains(Point3 { x: 0, y: 0, z: 0 }));
+        assert!(volume.contains(Point3 { x: 2, y: 3, z: 1 }));
+        assert!(!volume.contains(Point3 { x: 2, y: 3, z: 2 }));
+        assert!(!volume.contains(Point3 { x: 0, y: 0, z: -1 }));
+    }
+}
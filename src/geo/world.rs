@@ -0,0 +1,196 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use super::grid::Grid;
+use super::location::{Coord, Point};
+use super::mask::Mask;
+use super::orientation::Orientation;
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Shape {
+    Rectangle(Point, Point), // from the lower left to the upper right corner
+    Hexagon(Coord),          // of a given radius, centred on (radius, radius)
+    Mask(Mask),              // any cells at all, which can leave holes in the world
+}
+
+// The cells robots can move between without getting lost
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct World {
+    pub grid: Grid,
+    pub shape: Shape,
+}
+
+impl World {
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    // A square grid from the origin to the upper right corner
+    pub fn rectangle(upper_right: Point) -> World {
+        World::between(World::ORIGIN, upper_right)
+    }
+
+    pub fn between(lower_left: Point, upper_right: Point) -> World {
+        World {
+            grid: Grid::Square,
+            shape: Shape::Rectangle(lower_left, upper_right),
+        }
+    }
+
+    pub fn contains(&self, position: Point) -> bool {
+        match (self.grid, &self.shape) {
+            (Grid::Square, Shape::Rectangle(lower_left, upper_right)) => {
+                (lower_left.x..=upper_right.x).contains(&position.x)
+                    && (lower_left.y..=upper_right.y).contains(&position.y)
+            }
+            // hex rows are staggered by half a cell, so to keep the world rectangular,
+            // every other row starts one cell further along the x axis
+            // (worked out in 128 bits, so cells at the edge of the range don't overflow)
+            (Grid::Hex, Shape::Rectangle(lower_left, upper_right)) => {
+                let column = position.x as i128 - position.y.div_euclid(2) as i128;
+                (lower_left.x as i128..=upper_right.x as i128).contains(&column)
+                    && (lower_left.y..=upper_right.y).contains(&position.y)
+            }
+            (_, Shape::Hexagon(radius)) => {
+                let radius = *radius as i128;
+                let (q, r) = (position.x as i128 - radius, position.y as i128 - radius);
+                q.abs().max(r.abs()).max((q - r).abs()) <= radius
+            }
+            (_, Shape::Mask(mask)) => mask.contains(position),
+        }
+    }
+
+    // The corners of the smallest rectangle of columns and rows covering the world, e.g.
+    // to draw it. Rows on a hex grid are staggered, see position.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        match &self.shape {
+            Shape::Rectangle(lower_left, upper_right) => Some((*lower_left, *upper_right)),
+            Shape::Hexagon(radius) => Some((
+                World::ORIGIN,
+                Point {
+                    x: radius * 2,
+                    y: radius * 2,
+                },
+            )),
+            Shape::Mask(mask) => mask.bounds(),
+        }
+    }
+
+    // The position of the cell in a column and row of the world's bounds
+    pub fn position(&self, column: Coord, row: Coord) -> Point {
+        match (self.grid, &self.shape) {
+            (Grid::Hex, Shape::Rectangle(..)) => Point {
+                x: column + row.div_euclid(2), // see contains
+                y: row,
+            },
+            _ => Point { x: column, y: row },
+        }
+    }
+
+    // Which side of the world's bounds a cell lies beyond, e.g. NE past the upper right
+    // corner, or nothing if it's within them. Coordinates are given in 128 bits, so cells
+    // beyond the range of coordinates can be too.
+    pub fn beyond(&self, x: i128, y: i128) -> Option<Orientation> {
+        let (lower_left, upper_right) = self.bounds()?;
+        let column = match (self.grid, &self.shape) {
+            (Grid::Hex, Shape::Rectangle(..)) => x - y.div_euclid(2), // see contains
+            _ => x,
+        };
+
+        let north = y > upper_right.y as i128;
+        let south = y < lower_left.y as i128;
+        let east = column > upper_right.x as i128;
+        let west = column < lower_left.x as i128;
+
+        match (north, east, south, west) {
+            (true, true, _, _) => Some(Orientation::NorthEast),
+            (true, _, _, true) => Some(Orientation::NorthWest),
+            (true, _, _, _) => Some(Orientation::North),
+            (_, true, true, _) => Some(Orientation::SouthEast),
+            (_, _, true, true) => Some(Orientation::SouthWest),
+            (_, _, true, _) => Some(Orientation::South),
+            (_, true, _, _) => Some(Orientation::East),
+            (_, _, _, true) => Some(Orientation::West),
+            _ => None,
+        }
+    }
+
+    // How many cells a rectangular world has, so something can be kept for each of them
+    // in a flat array (see cell_index)
+    pub fn cell_count(&self) -> Option<usize> {
+        match &self.shape {
+            Shape::Rectangle(lower_left, upper_right) => {
+                let width = (upper_right.x as i128 - lower_left.x as i128 + 1).max(0);
+                let height = (upper_right.y as i128 - lower_left.y as i128 + 1).max(0);
+                usize::try_from(width * height).ok()
+            }
+            _ => None,
+        }
+    }
+
+    // Numbers the cells of a rectangular world row by row, from the lower left corner
+    pub fn cell_index(&self, position: Point) -> Option<usize> {
+        let (lower_left, upper_right) = match &self.shape {
+            Shape::Rectangle(lower_left, upper_right) if self.contains(position) => {
+                (lower_left, upper_right)
+            }
+            _ => return None,
+        };
+
+        let column = match self.grid {
+            Grid::Square => position.x,
+            Grid::Hex => position.x - position.y.div_euclid(2),
+        };
+        let width = (upper_right.x - lower_left.x + 1) as usize;
+
+        Some((position.y - lower_left.y) as usize * width + (column - lower_left.x) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_rectangles_have_staggered_rows() {
+        let world = World {
+            grid: Grid::Hex,
+            shape: Shape::Rectangle(World::ORIGIN, Point { x: 2, y: 3 }),
+        };
+
+        let row = |y| {
+            (-2..6)
+                .filter(|&x| world.contains(Point { x, y }))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(row(0), vec![0, 1, 2]);
+        assert_eq!(row(1), vec![0, 1, 2]);
+        assert_eq!(row(2), vec![1, 2, 3]);
+        assert_eq!(row(3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn rectangles_extend_below_the_origin() {
+        let world = World::between(Point { x: -2, y: -1 }, Point { x: 1, y: 1 });
+
+        assert!(world.contains(Point { x: -2, y: -1 }));
+        assert!(world.contains(Point { x: 1, y: 1 }));
+        assert!(!world.contains(Point { x: -3, y: 0 }));
+        assert!(!world.contains(Point { x: 0, y: -2 }));
+    }
+
+    #[test]
+    fn hexagons_have_six_sides() {
+        let world = World {
+            grid: Grid::Hex,
+            shape: Shape::Hexagon(1),
+        };
+
+        let cells = (-1..4)
+            .flat_map(|x| (-1..4).map(move |y| Point { x, y }))
+            .filter(|&p| world.contains(p))
+            .count();
+
+        assert_eq!(cells, 7);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bundle::{EXPECTED, PLAN};
+
+// A golden-file case: a directory with a plan, and the outcomes running it should give,
+// laid out like the cases in a generated bundle
+pub struct Case {
+    pub name: String, // the case's directory, relative to where the cases were found
+    pub dir: PathBuf,
+}
+
+// Finds every case in a directory and the directories below it, ordered by name
+pub fn find(dir: &Path) -> io::Result<Vec<Case>> {
+    let mut dirs = vec![dir.to_path_buf()];
+    let mut cases = Vec::new();
+
+    while let Some(next) = dirs.pop() {
+        if next.join(PLAN).is_file() {
+            let name = match next.strip_prefix(dir) {
+                Ok(relative) if relative != Path::new("") => relative.display().to_string(),
+                _ => ".".to_string(),
+            };
+            cases.push(Case {
+                name,
+                dir: next.clone(),
+            });
+        }
+
+        for entry in fs::read_dir(&next)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+impl Case {
+    pub fn plan(&self) -> PathBuf {
+        self.dir.join(PLAN)
+    }
+
+    // The stored outcomes, if there are any yet
+    pub fn expected(&self) -> Option<String> {
+        fs::read_to_string(self.dir.join(EXPECTED)).ok()
+    }
+
+    // Replaces the stored outcomes, e.g. once a change in behaviour is known to be right
+    pub fn update(&self, actual: &str) -> io::Result<()> {
+        fs::write(self.dir.join(EXPECTED), actual)
+    }
+}
+
+// How running a case went
+pub struct Verdict {
+    pub case: String,
+    pub expected: Option<String>,
+    pub actual: Result<String, String>, // the output, unless the case couldn't be run at all
+    pub updated: bool,
+}
+
+impl Verdict {
+    pub fn passed(&self) -> bool {
+        match (&self.expected, &self.actual) {
+            (Some(expected), Ok(actual)) => expected == actual,
+            _ => false,
+        }
+    }
+
+    // How many of the expected lines were matched by the same line of the actual output
+    pub fn matching(&self) -> (usize, usize) {
+        let expected = self.expected.as_deref().unwrap_or("");
+        let actual = self.actual.as_deref().unwrap_or("");
+
+        let matching = expected
+            .lines()
+            .zip(actual.lines())
+            .filter(|(e, a)| e == a)
+            .count();
+
+        (matching, expected.lines().count())
+    }
+}
+
+// Runs a candidate implementation, a shell command given the plan on STDIN, returning
+// what it printed. Candidates still running after the timeout are killed, along with any
+// processes they started.
+pub fn run_candidate(command: &str, plan: &Path, timeout: Duration) -> Result<String, String> {
+    let plan = fs::read(plan).map_err(|e| format!("Can't read {}: {}", plan.display(), e))?;
+    let failed = |e: io::Error| format!("Can't run {}: {}", command, e);
+
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+    let mut child = shell.spawn().map_err(failed)?;
+
+    // the plan is written while the output is read, as candidates printing outcomes as
+    // they go would otherwise fill the pipe and wait for a reader forever. Candidates
+    // which stop reading early make writing the rest of the plan fail, which doesn't
+    // matter as long as their output is right.
+    let mut stdin = child.stdin.take().unwrap();
+    thread::spawn(move || stdin.write_all(&plan));
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(failed)? {
+            Some(status) => break status,
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            None => {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(format!("{} timed out after {:?}", command, timeout));
+            }
+        }
+    };
+
+    let output = reader.join().unwrap().map_err(failed)?;
+    if status.success() {
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    } else {
+        Err(format!("{} failed ({})", command, status))
+    }
+}
+
+// Kills a candidate's whole process group, which it leads, so nothing it started is left
+// running. Elsewhere only the candidate itself can be killed.
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // kill only sends a signal, whatever the process
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(expected: &str, actual: &str) -> Verdict {
+        Verdict {
+            case: "case".to_string(),
+            expected: Some(expected.to_string()),
+            actual: Ok(actual.to_string()),
+            updated: false,
+        }
+    }
+
+    #[test]
+    fn counts_matching_lines() {
+        let passed = verdict("1 1 E\n3 3 N LOST\n", "1 1 E\n3 3 N LOST\n");
+        let failed = verdict("1 1 E\n3 3 N LOST\n2 3 S\n", "1 1 E\n3 3 N\n2 3 S\n");
+
+        assert!(passed.passed());
+        assert!(!failed.passed());
+        assert_eq!(failed.matching(), (2, 3));
+    }
+
+    #[test]
+    fn runs_candidates_streaming_their_output() {
+        let plan = std::env::temp_dir().join(format!("golden-plan-{}", std::process::id()));
+        // more than a pipe holds, so the plan can't all be written before reading
+        let text = "1 1 E\n".repeat(40_000);
+        fs::write(&plan, &text).unwrap();
+
+        let echoed = run_candidate("cat", &plan, Duration::from_secs(10));
+        fs::remove_file(&plan).unwrap();
+
+        assert_eq!(echoed.map(|output| output.len()), Ok(text.len()));
+    }
+
+    #[test]
+    fn stops_candidates_which_time_out() {
+        let dir = std::env::temp_dir().join(format!("golden-stuck-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(PLAN), "5 3\n").unwrap();
+
+        // the candidate leaves the work to a process of its own, which goes too
+        let late = dir.join("late");
+        let command = format!("(sleep 0.3; touch {}) & wait", late.display());
+        let stuck = run_candidate(&command, &dir.join(PLAN), Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(500));
+        let finished = late.exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(stuck.unwrap_err().contains("timed out"));
+        assert!(!finished);
+    }
+
+    #[test]
+    fn finds_cases_in_nested_directories() {
+        let dir = std::env::temp_dir().join(format!("golden-{}", std::process::id()));
+        for case in &["b", "a/deep", "a/empty"] {
+            fs::create_dir_all(dir.join(case)).unwrap();
+        }
+        fs::write(dir.join("b").join(PLAN), "5 3\n").unwrap();
+        fs::write(dir.join("a/deep").join(PLAN), "5 3\n").unwrap();
+
+        let names: Vec<_> = find(&dir).unwrap().into_iter().map(|c| c.name).collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, vec!["a/deep", "b"]);
+    }
+}
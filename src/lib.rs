@@ -0,0 +1,23 @@
+//! An example solution of the martian robots coding exercise, which can also be used to test implementations.
+
+pub mod bundle;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod checkpoint;
+pub mod commands;
+pub mod drone;
+pub mod generator;
+pub mod geo;
+pub mod golden;
+pub mod mission;
+pub mod parser;
+pub mod print;
+#[cfg(feature = "python")]
+#[allow(clippy::useless_conversion)] // in the code pyo3's macros generate
+mod python;
+pub mod repl;
+pub mod robot;
+pub mod scents;
+pub mod service;
+#[cfg(feature = "wasm")]
+pub mod wasm;
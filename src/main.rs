@@ -1,24 +1,204 @@
-mod generator;
-mod geo;
-mod mission;
-mod parser;
-mod print;
-mod robot;
-
-use std::io;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
 
 use structopt::StructOpt;
 
-use generator::Generator;
-use parser::{MissionOutcomes, MissionPlan};
+use martian_robots::bundle::{self, Bundle};
+use martian_robots::checkpoint::{Checkpoint, Source};
+use martian_robots::commands::CommandSet;
+use martian_robots::generator::{Generator, Settings};
+use martian_robots::geo::grid::Grid;
+use martian_robots::geo::location::{Coord, Point};
+use martian_robots::geo::orientation::{Compass, Orientation};
+use martian_robots::geo::world::World;
+use martian_robots::golden::{self, Verdict};
+use martian_robots::mission::{Collisions, Heatmap, Mission, MissionState, Obstacles, Target};
+use martian_robots::parser::{MissionOutcomes, MissionPlan, Plan};
+use martian_robots::print;
+use martian_robots::repl::Session;
+use martian_robots::robot::{self, Rover};
+use martian_robots::scents::{self, Scent};
+use martian_robots::service::{self, Service};
 
 /// An example solution of the martian robots coding exercise, which can also be used to test implementations.
-/// Consumes input from STDIN.
+/// Consumes input from the given files, or STDIN.
 #[derive(StructOpt)]
 #[structopt(author = "Viktor Charypar <charypar@gmail.com>", version = "0.2")]
 struct Opts {
     #[structopt(subcommand)]
     cmd: Option<Command>,
+    #[structopt(flatten)]
+    obstacles: ObstacleOpts,
+    #[structopt(flatten)]
+    scents: ScentOpts,
+    #[structopt(flatten)]
+    checkpoints: CheckpointOpts,
+    #[structopt(flatten)]
+    heatmap: HeatmapOpts,
+    /// Run all robots at once, each executing one command per tick
+    #[structopt(long, conflicts_with_all = &["checkpoint", "resume"])]
+    lockstep: bool,
+    /// Print the position of every robot after each tick of a lockstep run
+    #[structopt(long, requires = "lockstep")]
+    trace: bool,
+    /// Write the output to a file rather than STDOUT
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// Plan files to run, one after another, with - for STDIN (the default)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct ObstacleOpts {
+    /// Robots which finish their instructions stay on the grid and block later robots
+    #[structopt(long)]
+    obstacles: bool,
+    /// Lost robots also stay on the grid at their last position (implies --obstacles)
+    #[structopt(long)]
+    lost_obstacles: bool,
+    /// Report moves into a parked robot as a collision, instead of ignoring them
+    #[structopt(long)]
+    collisions: bool,
+}
+
+impl ObstacleOpts {
+    fn obstacles(&self) -> Option<Obstacles> {
+        if !(self.obstacles || self.lost_obstacles) {
+            return None;
+        }
+
+        let collisions = if self.collisions {
+            Collisions::Report
+        } else {
+            Collisions::Ignore
+        };
+
+        Some(Obstacles {
+            lost: self.lost_obstacles,
+            collisions,
+        })
+    }
+}
+
+#[derive(StructOpt)]
+struct ScentOpts {
+    /// Start with the scents saved by an earlier mission (as text, or JSON for a .json file)
+    #[structopt(long, parse(from_os_str))]
+    scents_in: Option<PathBuf>,
+    /// Save the scents left by the mission (as text, or JSON for a .json file)
+    #[structopt(long, parse(from_os_str))]
+    scents_out: Option<PathBuf>,
+}
+
+impl ScentOpts {
+    fn load(&self) -> Result<Vec<Scent>, String> {
+        match &self.scents_in {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+                scents::load(&text, scents::Format::for_path(path))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn save(&self, scents: &[Scent]) -> Result<(), String> {
+        match &self.scents_out {
+            Some(path) => fs::write(path, scents::save(scents, scents::Format::for_path(path)))
+                .map_err(|e| format!("Can't write {}: {}", path.display(), e)),
+            None => Ok(()),
+        }
+    }
+
+    fn used(&self) -> bool {
+        self.scents_in.is_some() || self.scents_out.is_some()
+    }
+}
+
+#[derive(StructOpt)]
+struct HeatmapOpts {
+    /// Draw how often robots visited each cell of the grid after each mission
+    #[structopt(long)]
+    heatmap: bool,
+    /// Save how often robots visited each cell of the grid as CSV
+    #[structopt(long, parse(from_os_str))]
+    heatmap_csv: Option<PathBuf>,
+}
+
+impl HeatmapOpts {
+    fn save(&self, world: &World, heatmap: &Heatmap) -> Result<(), String> {
+        let path = match &self.heatmap_csv {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut csv = Vec::new();
+        written(print::heatmap_csv(&mut csv, world, heatmap))?;
+        fs::write(path, csv).map_err(|e| format!("Can't write {}: {}", path.display(), e))
+    }
+
+    fn used(&self) -> bool {
+        self.heatmap || self.heatmap_csv.is_some()
+    }
+}
+
+#[derive(StructOpt)]
+struct CheckpointOpts {
+    /// Save the mission's progress to a file every so often, to resume from if interrupted
+    #[structopt(long, parse(from_os_str))]
+    checkpoint: Option<PathBuf>,
+    /// How many robots to dispatch between checkpoints
+    #[structopt(long, default_value = "10000")]
+    checkpoint_every: usize,
+    /// Resume from a checkpoint, given the same input again from the start
+    #[structopt(long, parse(from_os_str), conflicts_with = "scents-in")]
+    resume: Option<PathBuf>,
+}
+
+impl CheckpointOpts {
+    fn load(&self) -> Result<Option<Checkpoint>, String> {
+        match &self.resume {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| format!("Can't read {}: {}", path.display(), e))
+                .and_then(|json| Checkpoint::load(&json))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Saves a checkpoint every so many robots, or at the end of the mission. The output so
+    // far is flushed first, so it has everything the checkpoint says was done.
+    fn save<F>(
+        &self,
+        robots: usize,
+        done: bool,
+        out: &mut dyn Write,
+        checkpoint: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce() -> Checkpoint,
+    {
+        let path = match &self.checkpoint {
+            Some(path) if done || robots.is_multiple_of(self.checkpoint_every.max(1)) => path,
+            _ => return Ok(()),
+        };
+        written(out.flush())?;
+
+        // write the whole checkpoint before replacing the last one, in case of interruption
+        let partial = path.with_extension("partial");
+        fs::write(&partial, checkpoint().save())
+            .and_then(|_| fs::rename(&partial, path))
+            .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+    }
+
+    fn used(&self) -> bool {
+        self.checkpoint.is_some() || self.resume.is_some()
+    }
 }
 
 #[derive(StructOpt)]
@@ -26,54 +206,820 @@ enum Command {
     /// Generates pseudo-random robot runs for testing
     Generate(GenerateOpts),
     Verify(VerifyOpts),
+    /// Runs plans, reporting figures about each mission rather than the outcomes
+    Stats(StatsOpts),
+    /// Runs every plan in a directory of cases, comparing the outcomes with the expected ones
+    TestDir(TestDirOpts),
+    /// Serves HTTP endpoints on localhost to run plans, generate them and verify outcomes
+    Serve(ServeOpts),
+    /// Drives robots by hand, a few commands at a time, keeping scents between robots
+    Repl(ReplOpts),
+    /// Finds the shortest command string taking a robot to a target without it being lost
+    ///
+    /// Routes never use a move a scent would save the robot from, as it leaves the robot
+    /// where it was, so scents can't make a route any shorter.
+    PlanRoute(PlanRouteOpts),
 }
 
 #[derive(StructOpt)]
 struct GenerateOpts {
-    /// Only generate a given number of robots
+    /// Only generate a given number of robots (100 per case for a bundle)
     #[structopt(short = "n")]
     limit: Option<usize>,
+    /// Write a bundle of test cases to a directory, each with a plan and its expected outcomes
+    #[structopt(long, parse(from_os_str))]
+    bundle: Option<PathBuf>,
+    /// How many cases to write to a bundle, with seeds counting up from the first
+    #[structopt(long, default_value = "10")]
+    cases: usize,
+    #[structopt(flatten)]
+    generator: GeneratorOpts,
+}
+
+#[derive(StructOpt)]
+struct GeneratorOpts {
     /// Random seed to use
     #[structopt(short, default_value = "12345")]
     seed: u64,
+    /// Use all eight points of the compass, with half turns
+    #[structopt(long)]
+    eight_way: bool,
+    /// Generate a hex grid instead of a square one
+    #[structopt(long, conflicts_with = "eight-way")]
+    hex: bool,
+    /// Lower left corner of the world, e.g. -10,-5 (the world keeps its random size)
+    #[structopt(long, parse(try_from_str = parse_point), allow_hyphen_values = true)]
+    lower_left: Option<Point>,
+    /// Generate a world shaped like a random blob, rather than a rectangle
+    #[structopt(long, conflicts_with = "hex")]
+    blob: bool,
+    /// Generate a hexagonal world on the hex grid, rather than a rectangle
+    #[structopt(long, requires = "hex", conflicts_with = "lower-left")]
+    hexagon: bool,
+}
+
+#[derive(StructOpt)]
+struct StatsOpts {
+    /// Plan files to run, with - for STDIN (the default)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+#[derive(StructOpt)]
+struct TestDirOpts {
+    /// The directory of cases, each a directory with a plan.txt and an expected.txt
+    #[structopt(parse(from_os_str))]
+    dir: PathBuf,
+    /// A shell command to run the plans with instead, given each plan on STDIN
+    #[structopt(long)]
+    candidate: Option<String>,
+    /// Replace the expected outcomes of failing cases with the actual ones
+    #[structopt(long)]
+    update: bool,
+    /// How many seconds a candidate has to run each case before it's stopped
+    #[structopt(long, default_value = "60")]
+    timeout: u64,
+}
+
+#[derive(StructOpt)]
+struct ServeOpts {
+    /// Port to listen on
+    #[structopt(long, default_value = "8080")]
+    port: u16,
+}
+
+#[derive(StructOpt)]
+struct ReplOpts {
+    /// The grid to start on, given like the first line of a plan (5 3 by default)
+    grid: Vec<String>,
+}
+
+#[derive(StructOpt)]
+struct PlanRouteOpts {
+    /// Where the robot starts, e.g. 1,1
+    #[structopt(long, parse(try_from_str = parse_point), allow_hyphen_values = true)]
+    from: Point,
+    /// The way the robot faces at the start, e.g. E
+    #[structopt(long)]
+    facing: Orientation,
+    /// Where the robot should end up, e.g. 3,3
+    #[structopt(long, parse(try_from_str = parse_point), allow_hyphen_values = true)]
+    to: Point,
+    /// The way the robot should face at the end, any way if not given
+    #[structopt(long)]
+    to_facing: Option<Orientation>,
+    /// The commands the route can use
+    #[structopt(long, default_value = "LRF")]
+    commands: String,
+    /// The grid, given like the first line of a plan (5 3 by default)
+    grid: Vec<String>,
+}
+
+fn parse_point(text: &str) -> Result<Point, String> {
+    let coordinates = text
+        .split(',')
+        .map(|c| c.trim().parse::<Coord>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    match coordinates[..] {
+        [x, y] => Ok(Point { x, y }),
+        _ => Err(format!("Expected a point written as X,Y, got '{}'", text)),
+    }
+}
+
+impl GeneratorOpts {
+    fn settings(&self) -> Settings {
+        let compass = if self.eight_way {
+            Compass::Eight
+        } else {
+            Compass::Four
+        };
+
+        let grid = if self.hex { Grid::Hex } else { Grid::Square };
+
+        Settings {
+            compass,
+            grid,
+            lower_left: self.lower_left,
+            blob: self.blob,
+            hexagon: self.hexagon,
+            ..Settings::new(self.seed)
+        }
+    }
+
+    fn generator(&self) -> Result<Generator, String> {
+        self.settings().generator()
+    }
 }
 
 #[derive(StructOpt)]
 struct VerifyOpts {
-    /// Random seed to use
-    #[structopt(short, default_value = "12345")]
-    seed: u64,
+    #[structopt(flatten)]
+    generator: GeneratorOpts,
+    /// Files of outcomes to verify, each against the same robots, with - for STDIN (the default)
+    #[structopt(parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+// The files to read, with STDIN standing in when none are given
+fn inputs(files: &[PathBuf]) -> Vec<PathBuf> {
+    if files.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        files.to_vec()
+    }
+}
+
+fn open(path: &Path) -> Result<Box<dyn BufRead>, String> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    match File::open(path) {
+        Ok(file) => Ok(Box::new(BufReader::new(file))),
+        Err(e) => Err(format!("Can't read {}: {}", path.display(), e)),
+    }
+}
+
+// Writes to a file, or STDOUT. Resuming from a checkpoint carries on writing the file
+// after the lines for the robots it covers.
+fn output(path: &Option<PathBuf>, resumed: Option<usize>) -> Result<Box<dyn Write>, String> {
+    match path {
+        Some(path) => {
+            colored::control::set_override(false); // no escape codes in files
+            let file = match resumed {
+                Some(robots) => reopen(path, robots),
+                None => File::create(path),
+            };
+            file.map(|file| Box::new(BufWriter::new(file)) as Box<dyn Write>)
+                .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+        }
+        None => Ok(Box::new(io::stdout().lock())),
+    }
+}
+
+// Opens the output of an interrupted run to append to, keeping a line for each of the
+// robots dispatched and dropping anything written after the checkpoint
+fn reopen(path: &Path, robots: usize) -> io::Result<File> {
+    let text = fs::read(path)?;
+    let lines = text.iter().enumerate().filter(|&(_, &byte)| byte == b'\n');
+    let kept = match robots.checked_sub(1) {
+        None => 0,
+        Some(last) => match lines.map(|(i, _)| i + 1).nth(last) {
+            Some(end) => end,
+            None => {
+                let msg = format!("it has fewer than the {} lines checkpointed", robots);
+                return Err(io::Error::other(msg));
+            }
+        },
+    };
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.set_len(kept as u64)?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(file)
+}
+
+fn written<T>(result: io::Result<T>) -> Result<T, String> {
+    result.map_err(|e| format!("Can't write output: {}", e))
 }
 
 fn main() {
     let opts = Opts::from_args();
 
-    let stdin = io::stdin();
-    let mut input = stdin.lock();
+    if let Err(msg) = execute(&opts) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}
+
+fn execute(opts: &Opts) -> Result<(), String> {
+    let resumed = opts.checkpoints.load()?.map(|checkpoint| checkpoint.robots);
+    let mut out = output(&opts.output, resumed)?;
+    let obstacles = opts.obstacles.obstacles();
+    let files = match &opts.cmd {
+        Some(Command::Verify(verify)) => inputs(&verify.files),
+        Some(Command::Stats(stats)) => inputs(&stats.files),
+        _ => inputs(&opts.files),
+    };
 
+    if files.len() > 1 && (opts.checkpoints.used() || opts.scents.used()) {
+        return Err("Checkpoints and scents can only be used with a single input".to_string());
+    }
     match opts.cmd {
-        Some(Command::Generate(opts)) => {
-            let gen = Generator::new(opts.seed);
+        Some(Command::TestDir(_)) if opts.checkpoints.used() || opts.scents.used() => {
+            return Err("Checkpoints and scents can't be used when testing cases".to_string());
+        }
+        Some(Command::Stats(_)) if opts.checkpoints.used() => {
+            return Err("Checkpoints can't be used for stats".to_string());
+        }
+        None if opts.checkpoints.used() && opts.heatmap.used() => {
+            return Err("Heatmaps can't be drawn for checkpointed runs".to_string());
+        }
+        Some(Command::Serve(_))
+            if opts.checkpoints.used() || opts.scents.used() || opts.heatmap.used() =>
+        {
+            return Err("Checkpoints, scents and heatmaps can't be used when serving".to_string());
+        }
+        Some(Command::Repl(_)) if obstacles.is_some() || opts.lockstep => {
+            return Err("Robots driven by hand don't park or move in lockstep".to_string());
+        }
+        Some(Command::PlanRoute(_)) if obstacles.is_some() || opts.lockstep => {
+            return Err("Routes are planned for a single robot on its own".to_string());
+        }
+        _ => (),
+    }
+
+    match &opts.cmd {
+        Some(Command::Generate(generate)) if generate.bundle.is_some() => {
+            let settings = generate.generator.settings();
+            let robots = generate.limit.unwrap_or(100);
+            let dir = generate.bundle.as_ref().unwrap();
+
+            Bundle::generate(&settings, generate.cases, robots, obstacles).save(dir)?;
+        }
+        Some(Command::Generate(generate)) => {
+            let gen = generate.generator.generator()?;
+
+            written(match generate.limit {
+                Some(limit) => print::plan(&mut out, &gen.world(), gen.take(limit)),
+                None => print::plan(&mut out, &gen.world(), gen),
+            })?;
+        }
+        Some(Command::TestDir(test)) => {
+            let verdicts = test_dir(test, obstacles, opts)?;
+            written(print::verdicts(&mut out, &verdicts))?;
+
+            let failed = verdicts
+                .iter()
+                .filter(|v| !v.passed() && !v.updated)
+                .count();
+            if failed > 0 {
+                written(out.flush())?;
+                return Err(format!("{} of {} cases failed", failed, verdicts.len()));
+            }
+        }
+        Some(Command::Serve(serve)) => serve_http(serve, obstacles)?,
+        Some(Command::Repl(repl)) => drive(repl, &opts.scents, &mut out)?,
+        Some(Command::PlanRoute(route)) => {
+            let commands = plan_route(route, &opts.scents.load()?)?;
+            let letters: String = commands.iter().map(ToString::to_string).collect();
+            written(writeln!(out, "{}", letters))?;
+        }
+        Some(Command::Verify(verify)) if opts.checkpoints.used() => {
+            let mut input = open(&files[0])?;
+            verify_checkpointed(&mut input, &mut out, verify, obstacles, &opts.checkpoints)?;
+        }
+        Some(Command::Verify(verify)) => {
+            for path in &files {
+                if files.len() > 1 {
+                    written(print::file(&mut out, path))?;
+                }
+                let mut input = match open(path) {
+                    Ok(input) => input,
+                    Err(msg) => {
+                        eprintln!("{}", msg);
+                        continue;
+                    }
+                };
+
+                let actual_outcomes = MissionOutcomes::read(&mut input);
+                let generator = verify.generator.generator()?;
+                let expected_outcomes = match obstacles {
+                    Some(obstacles) => generator.mission().with_obstacles(obstacles),
+                    None => generator.mission(),
+                };
+
+                let tally = written(print::checks(
+                    &mut out,
+                    expected_outcomes.zip(actual_outcomes),
+                ))?;
+                if files.len() > 1 {
+                    written(print::summary(&mut out, tally))?;
+                }
+            }
+        }
+        None if opts.checkpoints.used() => {
+            let mut input = open(&files[0])?;
+            run_checkpointed(&mut input, &mut out, obstacles, opts)?;
+        }
+        None | Some(Command::Stats(_)) => {
+            for path in &files {
+                if files.len() > 1 {
+                    written(print::file(&mut out, path))?;
+                }
+                // a broken file doesn't stop the rest from being run
+                let errors = &mut io::stderr();
+                let run = open(path)
+                    .and_then(|mut input| run_plans(&mut input, &mut out, errors, obstacles, opts));
+                if let Err(msg) = run {
+                    eprintln!("{}", msg);
+                }
+            }
+        }
+    }
+
+    written(out.flush())
+}
+
+// The grid given on the command line, like the first line of a plan
+fn grid(words: &[String]) -> Result<(String, World), String> {
+    let grid = match words.join(" ") {
+        grid if grid.is_empty() => "5 3".to_string(),
+        grid => grid,
+    };
+    let world = MissionPlan::read(&mut grid.as_bytes())?.world;
+
+    Ok((grid, world))
+}
+
+// Finds a route for a robot in a world which may already have scents
+fn plan_route(route: &PlanRouteOpts, scents: &[Scent]) -> Result<Vec<robot::Command>, String> {
+    let (_, world) = grid(&route.grid)?;
+    let known = CommandSet::new();
+    let commands = route
+        .commands
+        .chars()
+        .map(|letter| {
+            known
+                .command(letter)
+                .ok_or_else(|| format!("Unknown command '{}'", letter))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mission: Mission<_, (robot::Robot, Vec<robot::Command>)> =
+        Mission::in_world(world, Vec::new()).with_scents(scents.iter().copied().map(Scent::into));
+    let start = robot::Robot {
+        position: route.from,
+        facing: route.facing,
+    };
+    let target = Target {
+        position: route.to,
+        facing: route.to_facing,
+    };
+
+    mission.plan_route(start, target, &commands)
+}
+
+// Reads lines from STDIN until it closes or the user quits, starting with any scents
+// loaded and saving the scents left at the end
+fn drive(repl: &ReplOpts, scents: &ScentOpts, out: &mut dyn Write) -> Result<(), String> {
+    let (grid, world) = grid(&repl.grid)?;
+    let mut session = Session::new(world).with_scents(&scents.load()?);
+
+    written(write!(
+        out,
+        "Grid {}, type help to see what you can do\n> ",
+        grid
+    ))?;
+    written(out.flush())?;
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|e| format!("Can't read input: {}", e))?;
+        if !written(session.handle(&line, out))? {
+            break;
+        }
+        written(write!(out, "> "))?;
+        written(out.flush())?;
+    }
+
+    scents.save(&session.state().scents)
+}
+
+// Answers requests until the process is stopped
+fn serve_http(serve: &ServeOpts, obstacles: Option<Obstacles>) -> Result<(), String> {
+    let address = ("127.0.0.1", serve.port);
+    let server = tiny_http::Server::http(address)
+        .map_err(|e| format!("Can't listen on port {}: {}", serve.port, e))?;
+    let service = match obstacles {
+        Some(obstacles) => Service::new().with_obstacles(obstacles),
+        None => Service::new(),
+    };
+
+    colored::control::set_override(false); // no escape codes in responses
+    eprintln!("Listening on http://127.0.0.1:{}", serve.port);
+
+    for mut request in server.incoming_requests() {
+        let json = request.headers().iter().any(|h| {
+            h.field.equiv("Content-Type") && h.value.as_str().starts_with("application/json")
+        });
+        // a byte more than the service takes, so it can tell the body was too long
+        let mut reader = request.as_reader().take(service::MAX_BODY as u64 + 1);
+        let mut body = String::new();
+        let response = match reader.read_to_string(&mut body) {
+            Ok(_) => service.handle(&service::Request {
+                method: request.method().as_str(),
+                url: request.url(),
+                json,
+                body: &body,
+            }),
+            Err(e) => service::Response {
+                status: 400,
+                json: false,
+                body: format!("Can't read request: {}\n", e),
+            },
+        };
+
+        let content_type = if response.json {
+            "application/json"
+        } else {
+            "text/plain; charset=utf-8"
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", content_type).unwrap();
+        // from_data, as from_string would give the reply a second Content-Type
+        let reply = tiny_http::Response::from_data(response.body)
+            .with_status_code(response.status)
+            .with_header(header);
+
+        if let Err(e) = request.respond(reply) {
+            eprintln!("Can't respond: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs every mission in an input. Each starts with fresh scents, except that saved
+// scents are only ever loaded into and saved from the first.
+//
+// Each mission started by a '===' line is labelled the same way in the output, so a plan
+// with a single mission gives the outcomes alone. Robots which can't be read cut their
+// mission short, saying why on errors.
+fn run_plans<I: Read>(
+    input: &mut I,
+    out: &mut dyn Write,
+    errors: &mut dyn Write,
+    obstacles: Option<Obstacles>,
+    opts: &Opts,
+) -> Result<(), String> {
+    let mut next = Some(Plan::read(input));
+    let mut first = true;
+
+    while let Some(plan) = next.take() {
+        let plan = plan?;
+        let name = match &plan {
+            Plan::Surface(plan) => plan.name.clone(),
+            Plan::Flight(plan) => plan.name.clone(),
+        };
+        written(print::label(out, name.as_deref()))?;
+
+        next = run_plan(plan, out, errors, obstacles, opts, first)?;
+        first = false;
+    }
 
-            match opts.limit {
-                Some(limit) => print::plan(gen.upper_right, gen.take(limit)),
-                None => print::plan(gen.upper_right, gen),
+    Ok(())
+}
+
+// Runs one mission of an input, returning the next plan in the input, if there is one
+fn run_plan<'a, I: Read>(
+    plan: Plan<'a, I>,
+    out: &mut dyn Write,
+    errors: &mut dyn Write,
+    obstacles: Option<Obstacles>,
+    opts: &Opts,
+    first: bool,
+) -> Result<Option<Result<Plan<'a, I>, String>>, String> {
+    Ok(match plan {
+        Plan::Surface(mut plan) => {
+            let scents = match opts.scents.load()? {
+                scents if first => scents.into_iter().map(Into::into).collect(),
+                _ => Vec::new(),
             };
+
+            let world = plan.world.clone();
+            let aftermath = run(
+                world.clone(),
+                &mut plan,
+                scents,
+                out,
+                errors,
+                obstacles,
+                opts,
+            )?;
+
+            if let Some(heatmap) = &aftermath.heatmap {
+                if opts.heatmap.heatmap {
+                    written(print::heatmap(out, &world, heatmap))?;
+                }
+                if first {
+                    opts.heatmap.save(&world, heatmap)?;
+                }
+            }
+            if first {
+                let left: Vec<Scent> = aftermath.scents.into_iter().map(Scent::from).collect();
+                if let Err(msg) = opts.scents.save(&left) {
+                    eprintln!("{}", msg);
+                }
+            }
+            plan.next_plan()
         }
-        Some(Command::Verify(opts)) => {
-            let actual_outcomes = MissionOutcomes::read(&mut input);
-            let expected_outcomes = Generator::new(opts.seed).mission();
+        Plan::Flight(_) if opts.scents.used() => {
+            return Err("Scents can only be saved for missions on a grid".to_string());
+        }
+        Plan::Flight(_) if opts.heatmap.used() => {
+            return Err("Heatmaps can only be drawn for missions on a grid".to_string());
+        }
+        Plan::Flight(mut plan) => {
+            run(
+                plan.volume.clone(),
+                &mut plan,
+                Vec::new(),
+                out,
+                errors,
+                obstacles,
+                opts,
+            )?;
+            plan.next_plan()
+        }
+    })
+}
+
+// Runs each case in a directory, with this implementation or a candidate one
+fn test_dir(
+    test: &TestDirOpts,
+    obstacles: Option<Obstacles>,
+    opts: &Opts,
+) -> Result<Vec<Verdict>, String> {
+    let cases =
+        golden::find(&test.dir).map_err(|e| format!("Can't read {}: {}", test.dir.display(), e))?;
+    // cases in a generated bundle are run with the obstacles they were generated with
+    let bundle = if test.dir.join(bundle::MANIFEST).is_file() {
+        Bundle::load(&test.dir)?
+    } else {
+        Bundle::default()
+    };
+
+    let verdicts = cases.iter().map(|case| {
+        let obstacles = match bundle.cases.iter().find(|c| c.name == case.name) {
+            Some(generated) => generated.obstacles,
+            None => obstacles,
+        };
 
-            print::checks(expected_outcomes.zip(actual_outcomes));
+        let actual = match &test.candidate {
+            Some(command) => {
+                let timeout = Duration::from_secs(test.timeout);
+                golden::run_candidate(command, &case.plan(), timeout)
+            }
+            // a case which can't be run fails, without stopping the others
+            None => {
+                let (mut actual, mut errors) = (Vec::new(), Vec::new());
+                open(&case.plan())
+                    .and_then(|mut input| {
+                        run_plans(&mut input, &mut actual, &mut errors, obstacles, opts)
+                    })
+                    .map(|()| {
+                        // why robots couldn't be run shows in the diff, after their outcomes
+                        actual.extend(errors);
+                        String::from_utf8_lossy(&actual).into_owned()
+                    })
+            }
+        };
+
+        let mut verdict = Verdict {
+            case: case.name.clone(),
+            expected: case.expected(),
+            actual,
+            updated: false,
+        };
+
+        if test.update && !verdict.passed() {
+            if let Ok(actual) = &verdict.actual {
+                case.update(actual)
+                    .map_err(|e| format!("Can't update {}: {}", case.name, e))?;
+                verdict.updated = true;
+            }
         }
-        None => {
-            match MissionPlan::read(&mut input) {
-                Ok(plan) => {
-                    let mission = plan.mission();
-                    print::outcomes(mission)
+        Ok(verdict)
+    });
+
+    verdicts.collect()
+}
+
+type ScentList<R> = Vec<(<R as Rover>::Cell, <R as Rover>::Heading)>;
+
+// What a mission leaves behind once it's over
+struct Aftermath<R: Rover> {
+    scents: ScentList<R>,
+    heatmap: Option<Heatmap<R::Cell>>,
+}
+
+// Runs a plan for any kind of robot, one after another or in lockstep, returning the
+// scents left at the end, and the heatmap if one was asked for. The stats subcommand
+// prints the mission's stats instead of the outcomes.
+fn run<P, R>(
+    world: R::World,
+    plan: P,
+    scents: ScentList<R>,
+    out: &mut dyn Write,
+    errors: &mut dyn Write,
+    obstacles: Option<Obstacles>,
+    opts: &Opts,
+) -> Result<Aftermath<R>, String>
+where
+    P: Iterator<Item = Result<(R, Vec<robot::Command>), String>>,
+    R: Rover + Display,
+    R::Edge: Display + Ord,
+{
+    let stats_only = matches!(opts.cmd, Some(Command::Stats(_)));
+    let mut sink = io::sink();
+    let outcomes: &mut dyn Write = if stats_only { &mut sink } else { &mut *out };
+
+    let (scents, stats, heatmap) = if !opts.lockstep {
+        let mut mission: Mission<_, _, R> = Mission::in_world(world, plan).with_scents(scents);
+        if let Some(obstacles) = obstacles {
+            mission = mission.with_obstacles(obstacles);
+        }
+        if opts.heatmap.used() {
+            mission = mission.with_heatmap();
+        }
+
+        written(print::outcomes(outcomes, errors, &mut mission))?;
+        (
+            mission.scents(),
+            mission.stats().clone(),
+            mission.heatmap().cloned(),
+        )
+    } else {
+        let mut mission: Mission<_, (R, Vec<robot::Command>), R> =
+            Mission::in_world(world, Vec::new()).with_scents(scents);
+        if let Some(obstacles) = obstacles {
+            mission = mission.with_obstacles(obstacles);
+        }
+        if opts.heatmap.used() {
+            mission = mission.with_heatmap();
+        }
+
+        match plan.collect::<Result<Vec<_>, String>>() {
+            Ok(fleet) => {
+                let run = mission.dispatch_lockstep(&fleet);
+                if opts.trace {
+                    written(print::ticks(outcomes, &run.ticks))?;
                 }
-                Err(msg) => eprintln!("{}", msg),
+                let finished = run.outcomes.into_iter().map(Ok);
+                written(print::outcomes(outcomes, errors, finished))?;
+            }
+            Err(msg) => written(writeln!(errors, "{}", msg))?,
+        }
+        (
+            mission.scents(),
+            mission.stats().clone(),
+            mission.heatmap().cloned(),
+        )
+    };
+
+    if stats_only {
+        written(print::stats(out, &stats))?;
+    }
+    Ok(Aftermath { scents, heatmap })
+}
+
+// Runs a plan for robots on a grid, saving checkpoints along the way
+fn run_checkpointed<I: Read>(
+    input: &mut I,
+    out: &mut dyn Write,
+    obstacles: Option<Obstacles>,
+    opts: &Opts,
+) -> Result<(), String> {
+    let (plan, mut robots, state) = match opts.checkpoints.load()? {
+        Some(Checkpoint {
+            robots,
+            mission,
+            source: Source::Plan { world, offset },
+        }) => (MissionPlan::resume(input, world, offset)?, robots, mission),
+        Some(_) => return Err("The checkpoint is for verifying generated robots".to_string()),
+        None => {
+            let scents = opts.scents.load()?;
+            let state = MissionState {
+                scents,
+                occupied: Vec::new(),
             };
+            (MissionPlan::read(input)?, 0, state)
         }
+    };
+
+    let mut mission = Mission::in_world(plan.world.clone(), plan).with_state(state);
+    if let Some(obstacles) = obstacles {
+        mission = mission.with_obstacles(obstacles);
     }
+
+    let checkpoint = |mission: &Mission<MissionPlan<'_, I>, _>, robots| Checkpoint {
+        robots,
+        mission: mission.state(),
+        source: Source::Plan {
+            world: mission.world.clone(),
+            offset: mission.source().offset(),
+        },
+    };
+
+    while let Some(outcome) = mission.next() {
+        written(print::outcome(out, &outcome?))?;
+        robots += 1;
+        opts.checkpoints
+            .save(robots, false, out, || checkpoint(&mission, robots))?;
+    }
+
+    opts.checkpoints
+        .save(robots, true, out, || checkpoint(&mission, robots))?;
+    opts.scents.save(&mission.state().scents)?;
+
+    // a checkpoint only records where the first mission got to
+    match mission.into_source().next_plan() {
+        Some(_) => Err("Checkpointed runs can only have a single mission".to_string()),
+        None => Ok(()),
+    }
+}
+
+// Verifies outcomes against generated robots, saving checkpoints along the way
+fn verify_checkpointed<I: Read>(
+    input: &mut I,
+    out: &mut dyn Write,
+    verify: &VerifyOpts,
+    obstacles: Option<Obstacles>,
+    checkpoints: &CheckpointOpts,
+) -> Result<(), String> {
+    let (generator, mut actual, mut robots, state) = match checkpoints.load()? {
+        Some(Checkpoint {
+            robots,
+            mission,
+            source: Source::Generator { generator, offset },
+        }) => (
+            generator,
+            MissionOutcomes::resume(input, offset)?,
+            robots,
+            mission,
+        ),
+        Some(_) => return Err("The checkpoint is for a plan".to_string()),
+        None => (
+            verify.generator.generator()?,
+            MissionOutcomes::read(input),
+            0,
+            MissionState::default(),
+        ),
+    };
+
+    let mut expected = generator.mission().with_state(state);
+    if let Some(obstacles) = obstacles {
+        expected = expected.with_obstacles(obstacles);
+    }
+
+    let checkpoint = |expected: &Mission<Generator, _>, offset, robots| Checkpoint {
+        robots,
+        mission: expected.state(),
+        source: Source::Generator {
+            generator: expected.source().clone(),
+            offset,
+        },
+    };
+
+    while let Some(outcome) = actual.next() {
+        // the generator never runs out
+        written(print::check(out, expected.next().unwrap(), outcome?))?;
+        robots += 1;
+        checkpoints.save(robots, false, out, || {
+            checkpoint(&expected, actual.offset(), robots)
+        })?;
+    }
+
+    checkpoints.save(robots, true, out, || {
+        checkpoint(&expected, actual.offset(), robots)
+    })
 }
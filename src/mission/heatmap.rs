@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::geo::location::Point;
+
+// How many times robots visited each cell, counting the cell each robot starts in and
+// every cell it moves into
+#[derive(PartialEq, Clone, Debug)]
+pub struct Heatmap<C: Eq + Hash = Point> {
+    visits: HashMap<C, usize>,
+}
+
+impl<C: Copy + Eq + Hash> Heatmap<C> {
+    pub fn new() -> Heatmap<C> {
+        Heatmap {
+            visits: HashMap::new(),
+        }
+    }
+
+    pub fn visit(&mut self, cell: C) {
+        *self.visits.entry(cell).or_default() += 1;
+    }
+
+    pub fn visits(&self, cell: C) -> usize {
+        self.visits.get(&cell).copied().unwrap_or(0)
+    }
+
+    // The most visits to any one cell
+    pub fn hottest(&self) -> usize {
+        self.visits.values().copied().max().unwrap_or(0)
+    }
+
+    // How many cells were visited at all
+    pub fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+}
+
+impl<C: Copy + Eq + Hash> Default for Heatmap<C> {
+    fn default() -> Heatmap<C> {
+        Heatmap::new()
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use super::{Mission, Outcome, Step};
+use crate::robot::{Command, Robot, Rover};
+
+// The steps taken by every robot still active in one round of a lockstep run
+#[derive(Debug, PartialEq)]
+pub struct Tick<R = Robot> {
+    pub steps: Vec<(usize, Step<R>)>, // indexed by the robot's position in the fleet
+}
+
+pub struct Lockstep<R = Robot> {
+    pub outcomes: Vec<Outcome<R>>,
+    pub ticks: Vec<Tick<R>>,
+}
+
+impl<I, SourceItem, R> Mission<I, SourceItem, R>
+where
+    I: IntoIterator<Item = SourceItem>,
+    R: Rover,
+{
+    // Runs a whole fleet at once, every robot executing one command per tick.
+    //
+    // Robots are handled in fleet order within a tick, so a scent left by a lost robot
+    // warns any robot after it in the same tick. Robots never share a cell: a move into
+    // a cell which stays occupied is blocked, as is a move into a cell claimed by an
+    // earlier robot in the same tick, and two robots can't swap places.
+    pub fn dispatch_lockstep(&mut self, fleet: &[(R, Vec<Command>)]) -> Lockstep<R> {
+        let mut robots: Vec<R> = fleet.iter().map(|(robot, _)| *robot).collect();
+        let mut outcomes: Vec<Option<Outcome<R>>> = fleet
+            .iter()
+            .map(|(robot, commands)| match commands.len() {
+                0 => Some(Outcome::Success(*robot)),
+                _ => None,
+            })
+            .collect();
+        let mut ticks = Vec::new();
+        let mut paths = vec![0; fleet.len()];
+        for &robot in &robots {
+            self.visit(robot);
+        }
+
+        for t in 0.. {
+            let active: Vec<usize> = (0..fleet.len())
+                .filter(|&i| outcomes[i].is_none())
+                .collect();
+
+            if active.is_empty() {
+                break;
+            }
+
+            let mut steps = Vec::new();
+            let mut movers = Vec::new();
+
+            for &i in &active {
+                let r = robots[i];
+                let command = fleet[i].1[t];
+                // robots moving beyond the range of coordinates are off the grid too
+                let robot = r
+                    .execute(command, &self.commands, &self.world)
+                    .filter(|&robot| robot.cell() == r.cell() || self.on_grid(robot));
+
+                match robot {
+                    Some(robot) if robot.cell() == r.cell() => steps.push((i, Step::Moved(robot))),
+                    Some(robot) if self.occupied.contains(&robot.cell()) => {
+                        steps.push((i, self.bump(r)))
+                    }
+                    Some(robot) => movers.push((i, robot)),
+                    None if !self.commands.can_lose(command) => steps.push((i, Step::Blocked(r))),
+                    None => steps.push((i, self.fall(r, command))),
+                }
+            }
+
+            let lost: Vec<usize> = steps
+                .iter()
+                .filter(|(_, step)| matches!(step, Step::Lost(_)))
+                .map(|&(i, _)| i)
+                .collect();
+            let park_lost = self.obstacles.is_some_and(|o| o.lost);
+
+            // cells taken by robots at the start of the tick, apart from those just lost
+            let cells: HashMap<_, _> = (0..fleet.len())
+                .filter(|i| match outcomes[*i] {
+                    Some(Outcome::Lost(_)) => park_lost,
+                    _ => park_lost || !lost.contains(i),
+                })
+                .map(|i| (robots[i].cell(), i))
+                .collect();
+
+            for (i, step) in resolve(&robots, &cells, &movers) {
+                steps.push((i, step.unwrap_or_else(|| self.bump(robots[i]))));
+            }
+
+            steps.sort_by_key(|&(i, _)| i);
+
+            for &(i, step) in &steps {
+                if self.stats.step(robots[i], step) {
+                    paths[i] += 1;
+                    self.visit(step.robot());
+                }
+
+                match step {
+                    Step::Moved(robot) | Step::Scented(robot) | Step::Blocked(robot) => {
+                        robots[i] = robot;
+
+                        if t + 1 == fleet[i].1.len() {
+                            outcomes[i] = Some(Outcome::Success(robot));
+                        }
+                    }
+                    Step::Collided(robot) => outcomes[i] = Some(Outcome::Collided(robot)),
+                    Step::Lost(robot) => outcomes[i] = Some(Outcome::Lost(robot)),
+                }
+            }
+
+            ticks.push(Tick { steps });
+        }
+
+        let outcomes: Vec<Outcome<R>> = outcomes.into_iter().flatten().collect();
+
+        for (&outcome, &path) in outcomes.iter().zip(&paths) {
+            self.stats.outcome(outcome, path);
+            self.park(outcome);
+        }
+
+        Lockstep { outcomes, ticks }
+    }
+}
+
+// Decides which of the robots moving within the grid get to go ahead.
+//
+// Starting with every move allowed, moves are blocked until nothing changes: a move is
+// blocked if an earlier robot is allowed into the same cell, if the cell's occupant
+// isn't moving away, or if the occupant is moving into the mover's cell (a swap).
+fn resolve<R: Rover>(
+    robots: &[R],
+    cells: &HashMap<R::Cell, usize>,
+    movers: &[(usize, R)],
+) -> Vec<(usize, Option<Step<R>>)> {
+    let mut blocked = vec![false; movers.len()];
+
+    loop {
+        let mut changed = false;
+
+        for (m, &(i, robot)) in movers.iter().enumerate() {
+            if blocked[m] {
+                continue;
+            }
+
+            let contested = movers[..m]
+                .iter()
+                .zip(&blocked)
+                .any(|(&(_, other), &b)| !b && other.cell() == robot.cell());
+
+            let occupied = match cells.get(&robot.cell()) {
+                Some(&j) => match movers.iter().position(|&(k, _)| k == j) {
+                    Some(n) => blocked[n] || movers[n].1.cell() == robots[i].cell(),
+                    None => true,
+                },
+                None => false,
+            };
+
+            if contested || occupied {
+                blocked[m] = true;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    movers
+        .iter()
+        .zip(blocked)
+        .map(|(&(i, robot), blocked)| match blocked {
+            true => (i, None),
+            false => (i, Some(Step::Moved(robot))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::location::Point;
+    use crate::geo::orientation::Orientation::{East, North, South, West};
+    use crate::mission::tests::robot;
+    use crate::robot::Command::{Forward as F, Left as L, Right as R};
+
+    #[test]
+    fn robots_move_together() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let fleet = [
+            (robot(1, 1, East), vec![F, L, F]),
+            (robot(0, 3, West), vec![L, L]),
+        ];
+
+        let actual = mission.dispatch_lockstep(&fleet);
+
+        assert_eq!(
+            actual.outcomes,
+            vec![
+                Outcome::Success(robot(2, 2, North)),
+                Outcome::Success(robot(0, 3, East)),
+            ]
+        );
+        assert_eq!(
+            actual.ticks,
+            vec![
+                Tick {
+                    steps: vec![
+                        (0, Step::Moved(robot(2, 1, East))),
+                        (1, Step::Moved(robot(0, 3, South))),
+                    ]
+                },
+                Tick {
+                    steps: vec![
+                        (0, Step::Moved(robot(2, 1, North))),
+                        (1, Step::Moved(robot(0, 3, East))),
+                    ]
+                },
+                Tick {
+                    steps: vec![(0, Step::Moved(robot(2, 2, North)))]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn first_robot_wins_a_contested_cell() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let fleet = [(robot(0, 1, East), vec![F]), (robot(2, 1, West), vec![F])];
+
+        let actual = mission.dispatch_lockstep(&fleet);
+
+        assert_eq!(
+            actual.outcomes,
+            vec![
+                Outcome::Success(robot(1, 1, East)),
+                Outcome::Success(robot(2, 1, West)),
+            ]
+        );
+    }
+
+    #[test]
+    fn robots_cannot_swap_places() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let fleet = [(robot(1, 1, East), vec![F]), (robot(2, 1, West), vec![F])];
+
+        let actual = mission.dispatch_lockstep(&fleet);
+
+        assert_eq!(
+            actual.ticks,
+            vec![Tick {
+                steps: vec![
+                    (0, Step::Blocked(robot(1, 1, East))),
+                    (1, Step::Blocked(robot(2, 1, West))),
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn robots_follow_each_other() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let fleet = [
+            (robot(1, 1, East), vec![F, F]),
+            (robot(2, 1, East), vec![F, R]),
+        ];
+
+        let actual = mission.dispatch_lockstep(&fleet);
+
+        assert_eq!(
+            actual.outcomes,
+            vec![
+                Outcome::Success(robot(2, 1, East)),
+                Outcome::Success(robot(3, 1, South)),
+            ]
+        );
+    }
+
+    #[test]
+    fn scents_are_noticed_within_the_same_tick() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let fleet = [
+            (robot(3, 3, North), vec![F]),
+            (robot(3, 3, North), vec![F, R]),
+        ];
+
+        let actual = mission.dispatch_lockstep(&fleet);
+
+        assert_eq!(
+            actual.ticks[0],
+            Tick {
+                steps: vec![
+                    (0, Step::Lost(robot(3, 3, North))),
+                    (1, Step::Scented(robot(3, 3, North))),
+                ]
+            }
+        );
+    }
+}
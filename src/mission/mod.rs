@@ -0,0 +1,893 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::CommandSet;
+use crate::geo::location::Point;
+use crate::geo::world::World;
+use crate::robot::Command;
+use crate::robot::{Robot, Rover};
+use crate::scents::Scent;
+
+mod heatmap;
+mod lockstep;
+mod route;
+mod scent_map;
+mod stats;
+
+pub use heatmap::Heatmap;
+pub use lockstep::{Lockstep, Tick};
+pub use route::Target;
+pub use scent_map::ScentMap;
+pub use stats::Stats;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Outcome<R = Robot> {
+    Success(R),
+    Lost(R),
+    Collided(R),
+}
+
+// The effect of a single command on a robot
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Step<R = Robot> {
+    Moved(R),    // the command was carried out, including turns on the spot
+    Scented(R),  // the move would have lost the robot, but a scent warned it
+    Blocked(R),  // the move was not allowed (e.g. into another robot), so it was skipped
+    Collided(R), // the move ran into another robot, which ends the run
+    Lost(R),
+}
+
+impl<R: Copy> Step<R> {
+    // The robot after the step, wherever it ended up
+    pub fn robot(&self) -> R {
+        match *self {
+            Step::Moved(robot)
+            | Step::Scented(robot)
+            | Step::Blocked(robot)
+            | Step::Collided(robot)
+            | Step::Lost(robot) => robot,
+        }
+    }
+}
+
+// What happens when a robot tries to move into a cell occupied by a parked robot
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Collisions {
+    Ignore, // the move is skipped and the robot carries on
+    Report, // the robot stops where it is and the run ends as a collision
+}
+
+// Robots which stay parked on the grid once their run is over, blocking later robots
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Obstacles {
+    pub lost: bool, // lost robots also stay behind, at their last known position
+    pub collisions: Collisions,
+}
+
+// Everything a mission on a grid has learned from the robots dispatched so far, which
+// can be saved and used to carry on with the mission later
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MissionState {
+    pub scents: Vec<Scent>,
+    pub occupied: Vec<Point>, // cells taken by parked robots
+}
+
+// A mission for robots on a grid by default, or any other kind of rover
+pub struct Mission<I, X, R = Robot>
+where
+    I: IntoIterator<Item = X>,
+    R: Rover,
+{
+    pub world: R::World,
+    source: I,
+    scents: ScentMap<R>,
+    obstacles: Option<Obstacles>,
+    occupied: HashSet<R::Cell>,
+    commands: CommandSet,
+    stats: Stats<R>,
+    heatmap: Option<Heatmap<R::Cell>>,
+}
+
+impl<I, SourceItem> Mission<I, SourceItem>
+where
+    I: IntoIterator<Item = SourceItem>,
+{
+    pub fn new(upper_right: Point, source: I) -> Mission<I, SourceItem> {
+        Mission::in_world(World::rectangle(upper_right), source)
+    }
+
+    pub fn state(&self) -> MissionState {
+        let mut scents: Vec<Scent> = self.scents().into_iter().map(Scent::from).collect();
+        let mut occupied: Vec<Point> = self.occupied.iter().copied().collect();
+        scents.sort();
+        occupied.sort();
+
+        MissionState { scents, occupied }
+    }
+
+    // Carries on with a mission from a saved state
+    pub fn with_state(self, state: MissionState) -> Mission<I, SourceItem> {
+        let mut mission = self.with_scents(state.scents.into_iter().map(Scent::into));
+        mission.occupied.extend(state.occupied);
+        mission
+    }
+}
+
+impl<I, SourceItem, R> Mission<I, SourceItem, R>
+where
+    I: IntoIterator<Item = SourceItem>,
+    R: Rover,
+{
+    pub fn in_world(world: R::World, source: I) -> Mission<I, SourceItem, R> {
+        Mission {
+            scents: ScentMap::new(&world),
+            world,
+            source,
+            obstacles: None,
+            occupied: HashSet::new(),
+            commands: CommandSet::new(),
+            stats: Stats::default(),
+            heatmap: None,
+        }
+    }
+
+    pub fn with_commands(self, commands: CommandSet) -> Mission<I, SourceItem, R> {
+        Mission { commands, ..self }
+    }
+
+    pub fn with_obstacles(self, obstacles: Obstacles) -> Mission<I, SourceItem, R> {
+        Mission {
+            obstacles: Some(obstacles),
+            ..self
+        }
+    }
+
+    // Starts the mission with scents left by earlier missions in the same world
+    pub fn with_scents<S>(mut self, scents: S) -> Mission<I, SourceItem, R>
+    where
+        S: IntoIterator<Item = (R::Cell, R::Heading)>,
+    {
+        for (cell, heading) in scents {
+            self.scents.insert(&self.world, cell, heading);
+        }
+        self
+    }
+
+    // Keeps scents in a map, however small the world, e.g. to compare the two kinds of
+    // scent map's performance
+    pub fn with_sparse_scents(self) -> Mission<I, SourceItem, R> {
+        let scents = self.scents();
+        Mission {
+            scents: ScentMap::sparse(),
+            ..self
+        }
+        .with_scents(scents)
+    }
+
+    // Counts how often robots visit each cell, which slows the mission down a little
+    pub fn with_heatmap(self) -> Mission<I, SourceItem, R> {
+        Mission {
+            heatmap: Some(Heatmap::new()),
+            ..self
+        }
+    }
+
+    // Where the robots come from, e.g. to find out how far into a plan the mission is
+    pub fn source(&self) -> &I {
+        &self.source
+    }
+
+    // Ends the mission, handing back where the robots came from, e.g. to read on from it
+    pub fn into_source(self) -> I {
+        self.source
+    }
+
+    // All the scents left so far, in no particular order
+    pub fn scents(&self) -> Vec<(R::Cell, R::Heading)> {
+        self.scents.to_vec()
+    }
+
+    // Figures about the robots dispatched so far
+    pub fn stats(&self) -> &Stats<R> {
+        &self.stats
+    }
+
+    // How often robots visited each cell, for missions with a heatmap
+    pub fn heatmap(&self) -> Option<&Heatmap<R::Cell>> {
+        self.heatmap.as_ref()
+    }
+
+    pub fn dispatch(&mut self, robot: R, commands: &[Command]) -> Outcome<R> {
+        self.dispatch_with(robot, commands, |_| ())
+    }
+
+    // Dispatches a robot, showing each step it takes to a callback, e.g. to trace its path
+    pub fn dispatch_with<F>(&mut self, robot: R, commands: &[Command], mut on_step: F) -> Outcome<R>
+    where
+        F: FnMut(Step<R>),
+    {
+        let mut path = 0;
+        self.visit(robot);
+
+        let outcome = commands.iter().try_fold(robot, |r, c| {
+            let step = self.step(r, *c);
+            on_step(step);
+            if self.stats.step(r, step) {
+                path += 1;
+                self.visit(step.robot());
+            }
+
+            match step {
+                Step::Moved(robot) | Step::Scented(robot) | Step::Blocked(robot) => Ok(robot),
+                Step::Collided(robot) => Err(Outcome::Collided(robot)),
+                Step::Lost(robot) => Err(Outcome::Lost(robot)),
+            }
+        });
+
+        let outcome = match outcome {
+            Ok(robot) => Outcome::Success(robot),
+            Err(outcome) => outcome,
+        };
+
+        self.stats.outcome(outcome, path);
+        self.park(outcome);
+        outcome
+    }
+
+    fn step(&mut self, r: R, command: Command) -> Step<R> {
+        match self.preview(r, command) {
+            Step::Lost(r) => self.fall(r, command),
+            step => step,
+        }
+    }
+
+    // What a command would do to robot r, without leaving a scent if it would be lost
+    pub fn preview(&self, r: R, command: Command) -> Step<R> {
+        // robots moving beyond the range of coordinates are off the grid too
+        let robot = r
+            .execute(command, &self.commands, &self.world)
+            .filter(|&robot| self.on_grid(robot));
+
+        if let Some(robot) = robot {
+            // most missions don't park robots, so there's no need to look for them
+            let parked = !self.occupied.is_empty();
+            if parked && robot.cell() != r.cell() && self.occupied.contains(&robot.cell()) {
+                // moved robot would run into a parked one
+                return self.bump(r);
+            }
+
+            // moved robot is still on the grid, commit
+            return Step::Moved(robot);
+        }
+
+        if !self.commands.can_lose(command) {
+            // moved robot would be off the grid, but the command can't lose it
+            return Step::Blocked(r);
+        }
+
+        self.edge(r, r.heading(command, &self.commands))
+    }
+
+    fn visit(&mut self, robot: R) {
+        if let Some(heatmap) = &mut self.heatmap {
+            heatmap.visit(robot.cell());
+        }
+    }
+
+    fn on_grid(&self, robot: R) -> bool {
+        robot.within(&self.world)
+    }
+
+    // Robot r tried to move into an occupied cell...
+    fn bump(&self, r: R) -> Step<R> {
+        match self.obstacles.map(|o| o.collisions) {
+            // ...and stops there
+            Some(Collisions::Report) => Step::Collided(r),
+            // ...so we'll ignore the move
+            _ => Step::Blocked(r),
+        }
+    }
+
+    // Robot r tried to move off the grid, travelling towards heading...
+    fn fall(&mut self, r: R, command: Command) -> Step<R> {
+        let heading = r.heading(command, &self.commands);
+        let step = self.edge(r, heading);
+        if let Step::Lost(_) = step {
+            // ...and it's lost, but not before leaving a scent in its wake
+            self.scents.insert(&self.world, r.cell(), heading);
+            self.stats
+                .loss(r.edge(command, &self.commands, &self.world));
+        }
+        step
+    }
+
+    // Whether robot r would be lost travelling off the grid towards heading, or saved by a
+    // scent
+    fn edge(&self, r: R, heading: R::Heading) -> Step<R> {
+        if self.scents.contains(&self.world, r.cell(), heading) {
+            // a previous robot has left a scent, so the move is ignored
+            Step::Scented(r)
+        } else {
+            Step::Lost(r)
+        }
+    }
+
+    // Leaves a robot on the grid as an obstacle, if the mission calls for it
+    fn park(&mut self, outcome: Outcome<R>) {
+        if let Some(obstacles) = self.obstacles {
+            match outcome {
+                Outcome::Success(robot) | Outcome::Collided(robot) => {
+                    self.occupied.insert(robot.cell());
+                }
+                Outcome::Lost(robot) if obstacles.lost => {
+                    self.occupied.insert(robot.cell());
+                }
+                Outcome::Lost(_) => (),
+            }
+        }
+    }
+}
+
+// Running a mission with a reliable source
+impl<I, R> std::iter::Iterator for Mission<I, (R, Vec<Command>), R>
+where
+    I: Iterator<Item = (R, Vec<Command>)>,
+    R: Rover,
+{
+    type Item = Outcome<R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source
+            .next()
+            .map(|(robot, commands)| self.dispatch(robot, commands.as_ref()))
+    }
+}
+
+// Running a mission with a unreliable source
+impl<I, R> std::iter::Iterator for Mission<I, Result<(R, Vec<Command>), String>, R>
+where
+    I: Iterator<Item = Result<(R, Vec<Command>), String>>,
+    R: Rover,
+{
+    type Item = Result<Outcome<R>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source
+            .next()
+            .map(|item| item.map(|(robot, commands)| self.dispatch(robot, commands.as_ref())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandSet;
+    use crate::geo::grid::Grid;
+    use crate::geo::location::{Coord, Point};
+    use crate::geo::orientation::Orientation::{
+        self, East, North, NorthEast, NorthWest, South, West,
+    };
+    use crate::geo::world::Shape;
+    use crate::robot::Command::{Backward as B, Forward as F, Left as L, Right as R, UTurn as U};
+    use crate::robot::Command::{HalfLeft, HalfRight};
+    use crate::robot::Robot;
+
+    // A robot at x, y facing the given way, for the tests of missions and their parts
+    pub(super) fn robot(x: Coord, y: Coord, facing: Orientation) -> Robot {
+        Robot {
+            position: Point { x, y },
+            facing,
+        }
+    }
+
+    #[test]
+    fn simple_robot() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 1, y: 1 },
+            facing: East,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 1, y: 1 },
+            facing: East,
+        });
+        let actual = mission.dispatch(robot, &[R, F, R, F, R, F, R, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robot_is_lost() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: North,
+        };
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 3, y: 3 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[F, R, R, F, L, L, F, F, R, R, F, L, L]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robots_are_clever() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: North,
+        };
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 3, y: 3 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[F, R, R, F, L, L, F, F, R, R, F, L, L]);
+
+        assert_eq!(actual, expected);
+
+        let robot = Robot {
+            position: Point { x: 0, y: 3 },
+            facing: West,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 2, y: 3 },
+            facing: South,
+        });
+        let actual = mission.dispatch(robot, &[L, L, F, F, F, L, F, L, F, L]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robots_reversing_off_the_grid_leave_a_scent() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: South,
+        };
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 3, y: 3 },
+            facing: South,
+        });
+        let actual = mission.dispatch(robot, &[B, B]);
+
+        assert_eq!(actual, expected);
+
+        let robot = Robot {
+            position: Point { x: 3, y: 3 },
+            facing: East,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 3, y: 2 },
+            facing: South,
+        });
+        let actual = mission.dispatch(robot, &[L, F, U, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn scents_at_corners_only_cover_their_heading() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 4, y: 2 },
+            facing: NorthEast,
+        };
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 5, y: 3 },
+            facing: NorthEast,
+        });
+        let actual = mission.dispatch(robot, &[F, F]);
+
+        assert_eq!(actual, expected);
+
+        let robot = Robot {
+            position: Point { x: 5, y: 3 },
+            facing: East,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 5, y: 3 },
+            facing: East,
+        });
+        let actual = mission.dispatch(robot, &[HalfLeft, F, HalfRight]);
+
+        assert_eq!(actual, expected);
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 5, y: 3 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[L, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robots_are_lost_off_the_edge_of_the_range_of_coordinates() {
+        let upper_right = Point {
+            x: Coord::MAX,
+            y: 3,
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> = Mission::new(upper_right, Vec::new());
+        let robot = Robot {
+            position: Point {
+                x: Coord::MAX,
+                y: 1,
+            },
+            facing: East,
+        };
+
+        assert_eq!(mission.dispatch(robot, &[F]), Outcome::Lost(robot));
+        assert_eq!(
+            mission.dispatch(robot, &[F, L]).to_string(),
+            format!("{} 1 N", Coord::MAX)
+        );
+    }
+
+    #[test]
+    fn robots_are_warned_by_earlier_missions() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let robot = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: North,
+        };
+        mission.dispatch(robot, &[F, F]);
+
+        let scents = mission.scents();
+        assert_eq!(scents, vec![(Point { x: 3, y: 3 }, North)]);
+
+        let mut tomorrow: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_scents(scents);
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 3, y: 3 },
+            facing: North,
+        });
+        let actual = tomorrow.dispatch(robot, &[F, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn missions_keep_stats() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+
+        mission.dispatch(robot(1, 1, East), &[R, F, R, F, R, F, R, F]);
+        mission.dispatch(robot(3, 2, North), &[F, R, R, F, L, L, F, F, R, R, F, L, L]);
+        mission.dispatch(robot(0, 3, West), &[L, L, F, F, F, L, F, L, F, L]);
+
+        let stats = mission.stats();
+        assert_eq!((stats.robots, stats.lost, stats.scents), (3, 1, 1));
+        assert_eq!((stats.executed, stats.ignored, stats.scented), (25, 1, 1));
+        assert_eq!((stats.total_path, stats.longest_path), (11, 4));
+        assert_eq!(stats.losses.get(&North), Some(&1));
+        assert!((stats.loss_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn losses_are_counted_by_the_edge_crossed() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+
+        // heading north east over the top edge, and then over the upper right corner
+        mission.dispatch(robot(2, 3, NorthEast), &[F]);
+        mission.dispatch(robot(5, 3, NorthEast), &[F]);
+        // and turning east, over the right edge
+        mission.dispatch(robot(5, 1, NorthEast), &[HalfRight, F]);
+
+        let losses = &mission.stats().losses;
+        assert_eq!(losses.get(&North), Some(&1));
+        assert_eq!(losses.get(&NorthEast), Some(&1));
+        assert_eq!(losses.get(&East), Some(&1));
+    }
+
+    #[test]
+    fn heatmaps_count_visits() {
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 2, y: 2 }, Vec::new()).with_heatmap();
+        let robot = Robot {
+            position: Point { x: 0, y: 0 },
+            facing: North,
+        };
+
+        mission.dispatch(robot, &[F, R, F, L, F]);
+        mission.dispatch(robot, &[F, F, F, F]);
+
+        let heatmap = mission.heatmap().unwrap();
+        assert_eq!(heatmap.visits(Point { x: 0, y: 0 }), 2);
+        assert_eq!(heatmap.visits(Point { x: 0, y: 1 }), 2);
+        assert_eq!(heatmap.visits(Point { x: 1, y: 1 }), 1);
+        assert_eq!(heatmap.visits(Point { x: 1, y: 0 }), 0);
+        assert_eq!(heatmap.visits(Point { x: 0, y: 2 }), 1);
+        assert_eq!((heatmap.hottest(), heatmap.len()), (2, 5));
+    }
+
+    #[test]
+    fn missions_carry_on_from_a_saved_state() {
+        let obstacles = Obstacles {
+            lost: false,
+            collisions: Collisions::Ignore,
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_obstacles(obstacles);
+        let robot = Robot {
+            position: Point { x: 3, y: 2 },
+            facing: North,
+        };
+        mission.dispatch(robot, &[F, F]);
+        mission.dispatch(robot, &[R, F]);
+
+        let state = mission.state();
+        assert_eq!(state.occupied, vec![Point { x: 4, y: 2 }]);
+
+        let mut resumed: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new())
+                .with_obstacles(obstacles)
+                .with_state(state.clone());
+
+        assert_eq!(resumed.state(), state);
+        assert_eq!(
+            resumed.dispatch(robot, &[R, F]),
+            Outcome::Success(Robot {
+                position: Point { x: 3, y: 2 },
+                facing: East,
+            })
+        );
+    }
+
+    #[test]
+    fn robots_fall_off_hexagons() {
+        let world = World {
+            grid: Grid::Hex,
+            shape: Shape::Hexagon(1),
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> = Mission::in_world(world, Vec::new());
+        let robot = Robot {
+            position: Point { x: 1, y: 1 },
+            facing: East,
+        };
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 2, y: 1 },
+            facing: NorthEast,
+        });
+        let actual = mission.dispatch(robot, &[F, L, F, F]);
+
+        assert_eq!(actual, expected);
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 2, y: 2 },
+            facing: NorthWest,
+        });
+        let actual = mission.dispatch(robot, &[F, L, F, F, L, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn drones_leave_scents_in_the_air() {
+        use crate::drone::Drone;
+        use crate::geo::space::{Pitch, Point3, Volume};
+        use crate::robot::Command::{PitchDown, PitchUp};
+
+        let volume = Volume {
+            upper: Point3 { x: 5, y: 3, z: 2 },
+        };
+        let mut mission: Mission<_, (Drone, Vec<Command>), Drone> =
+            Mission::in_world(volume, Vec::new());
+        let drone = Drone {
+            position: Point3 { x: 1, y: 1, z: 1 },
+            facing: East,
+            pitch: Pitch::Level,
+        };
+
+        let expected = Outcome::Lost(Drone {
+            position: Point3 { x: 1, y: 1, z: 2 },
+            facing: East,
+            pitch: Pitch::Up,
+        });
+        let actual = mission.dispatch(drone, &[PitchUp, F, F]);
+
+        assert_eq!(actual, expected);
+
+        // the scent only covers flying up, not flying level at the same altitude
+        let expected = Outcome::Success(Drone {
+            position: Point3 { x: 2, y: 1, z: 2 },
+            facing: East,
+            pitch: Pitch::Level,
+        });
+        let actual = mission.dispatch(drone, &[PitchUp, F, F, PitchDown, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robots_fall_into_holes() {
+        use crate::geo::mask::Mask;
+
+        let world = World {
+            grid: Grid::Square,
+            shape: Shape::Mask(Mask::from_art(&["###", "#.#", "###"])),
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> = Mission::in_world(world, Vec::new());
+        let robot = Robot {
+            position: Point { x: 1, y: 0 },
+            facing: North,
+        };
+
+        let expected = Outcome::Lost(robot);
+        let actual = mission.dispatch(robot, &[F]);
+
+        assert_eq!(actual, expected);
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 2, y: 1 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[F, R, F, L, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn custom_commands_only_lose_robots_when_they_can() {
+        struct Jump(bool);
+
+        impl crate::commands::CustomCommand for Jump {
+            fn apply(&self, robot: Robot) -> Robot {
+                Robot {
+                    position: robot.position + robot.facing + robot.facing,
+                    facing: robot.facing,
+                }
+            }
+
+            fn can_lose(&self) -> bool {
+                self.0
+            }
+        }
+
+        let mut commands = CommandSet::new();
+        commands.register('J', Jump(true)).unwrap();
+        commands.register('K', Jump(false)).unwrap();
+
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_commands(commands);
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: North,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 1, y: 2 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[Command::Custom('K')]);
+
+        assert_eq!(actual, expected);
+
+        let expected = Outcome::Lost(Robot {
+            position: Point { x: 1, y: 2 },
+            facing: North,
+        });
+        let actual = mission.dispatch(robot, &[Command::Custom('J')]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parked_robots_are_avoided() {
+        let obstacles = Obstacles {
+            lost: false,
+            collisions: Collisions::Ignore,
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_obstacles(obstacles);
+        let robot = Robot {
+            position: Point { x: 1, y: 1 },
+            facing: East,
+        };
+
+        mission.dispatch(robot, &[L, R]);
+
+        let robot = Robot {
+            position: Point { x: 0, y: 2 },
+            facing: South,
+        };
+
+        let expected = Outcome::Success(Robot {
+            position: Point { x: 0, y: 0 },
+            facing: South,
+        });
+        let actual = mission.dispatch(robot, &[F, L, F, R, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn robots_collide_with_parked_robots() {
+        let obstacles = Obstacles {
+            lost: false,
+            collisions: Collisions::Report,
+        };
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_obstacles(obstacles);
+        let robot = Robot {
+            position: Point { x: 1, y: 1 },
+            facing: East,
+        };
+
+        mission.dispatch(robot, &[L, R]);
+
+        let robot = Robot {
+            position: Point { x: 0, y: 2 },
+            facing: South,
+        };
+
+        let expected = Outcome::Collided(Robot {
+            position: Point { x: 0, y: 1 },
+            facing: East,
+        });
+        let actual = mission.dispatch(robot, &[F, L, F, R, F]);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn lost_robots_only_park_when_asked_to() {
+        let lost = Robot {
+            position: Point { x: 3, y: 3 },
+            facing: North,
+        };
+        let robot = Robot {
+            position: Point { x: 3, y: 1 },
+            facing: North,
+        };
+
+        for (park_lost, expected) in [
+            (
+                false,
+                Outcome::Success(Robot {
+                    position: Point { x: 3, y: 3 },
+                    facing: North,
+                }),
+            ),
+            (
+                true,
+                Outcome::Collided(Robot {
+                    position: Point { x: 3, y: 2 },
+                    facing: North,
+                }),
+            ),
+        ] {
+            let obstacles = Obstacles {
+                lost: park_lost,
+                collisions: Collisions::Report,
+            };
+            let mut mission: Mission<_, (Robot, Vec<Command>)> =
+                Mission::new(Point { x: 5, y: 3 }, Vec::new()).with_obstacles(obstacles);
+
+            mission.dispatch(lost, &[F]);
+            let actual = mission.dispatch(robot, &[F, F]);
+
+            assert_eq!(actual, expected);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{Mission, Step};
+use crate::geo::location::Point;
+use crate::geo::orientation::Orientation;
+use crate::robot::{Command, Robot, Rover};
+
+// Searches give up after this many poses, in case the world is huge or unbounded
+const MAX_POSES: usize = 1 << 22;
+
+type Pose = (Point, Orientation);
+
+// How each pose was first reached: from which pose, with which command
+type Trail = HashMap<Pose, Option<(Pose, Command)>>;
+
+// Where a route should leave a robot, facing any way unless it says otherwise
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Target {
+    pub position: Point,
+    pub facing: Option<Orientation>,
+}
+
+impl Target {
+    fn reached(&self, robot: Robot) -> bool {
+        robot.position == self.position && self.facing.is_none_or(|f| f == robot.facing)
+    }
+}
+
+impl<I, SourceItem> Mission<I, SourceItem>
+where
+    I: IntoIterator<Item = SourceItem>,
+{
+    // The shortest list of commands, picked from the given ones, taking a robot to a
+    // target without it being lost or colliding with a parked robot. Robots are searched
+    // breadth first from the start, so each pose is only reached once by its fastest route.
+    //
+    // Scented moves are never useful: a command a scent saves the robot from leaves it
+    // where it was, so a route with one is always longer than the same route without it.
+    // Routes never contain one, however many scents the mission has, and there's no way to
+    // ask for them. Nothing is dispatched or scented.
+    pub fn plan_route(
+        &self,
+        start: Robot,
+        target: Target,
+        commands: &[Command],
+    ) -> Result<Vec<Command>, String> {
+        if !start.within(&self.world) {
+            return Err(format!("The start {} is off the grid", start.position));
+        }
+        if !self.world.contains(target.position) {
+            return Err(format!("The target {} is off the grid", target.position));
+        }
+
+        let mut reached = Trail::new();
+        let mut queue = VecDeque::new();
+
+        reached.insert(pose(start), None);
+        queue.push_back(start);
+
+        while let Some(robot) = queue.pop_front() {
+            if target.reached(robot) {
+                return Ok(route(&reached, pose(robot)));
+            }
+
+            for &command in commands {
+                let next = match self.preview(robot, command) {
+                    Step::Moved(next) => next,
+                    // the robot stays put, or comes to an end
+                    _ => continue,
+                };
+
+                if !reached.contains_key(&pose(next)) {
+                    if reached.len() >= MAX_POSES {
+                        return Err("The grid is too large to search for a route".to_string());
+                    }
+                    reached.insert(pose(next), Some((pose(robot), command)));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Err(format!(
+            "No safe route from {} to {}",
+            start, target.position
+        ))
+    }
+}
+
+fn pose(robot: Robot) -> Pose {
+    (robot.position, robot.facing)
+}
+
+// Follows the way back from a pose to the start, returning the commands in order
+fn route(reached: &Trail, mut last: Pose) -> Vec<Command> {
+    let mut commands = Vec::new();
+    while let Some(&Some((previous, command))) = reached.get(&last) {
+        commands.push(command);
+        last = previous;
+    }
+
+    commands.reverse();
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::orientation::Orientation::*;
+    use crate::mission::tests::robot;
+    use crate::robot::Command::*;
+
+    #[test]
+    fn finds_the_shortest_route() {
+        let mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new());
+        let target = Target {
+            position: Point { x: 3, y: 3 },
+            facing: Some(West),
+        };
+
+        let commands = mission.plan_route(robot(1, 1, East), target, &[Left, Right, Forward]);
+
+        assert_eq!(
+            commands,
+            Ok(vec![Forward, Forward, Left, Forward, Forward, Left])
+        );
+
+        let anyway = Target {
+            facing: None,
+            ..target
+        };
+        assert_eq!(
+            mission.plan_route(robot(1, 1, East), anyway, &[Left, Right, Forward]),
+            Ok(vec![Forward, Forward, Left, Forward, Forward])
+        );
+    }
+
+    #[test]
+    fn routes_never_leave_the_grid() {
+        let mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::new(Point { x: 5, y: 3 }, Vec::new())
+                .with_scents(vec![(Point { x: 0, y: 0 }, South)]);
+        let target = Target {
+            position: Point { x: 0, y: 3 },
+            facing: None,
+        };
+
+        // the scent doesn't matter, as a route never needs to be saved by one
+        let commands = mission.plan_route(robot(0, 0, North), target, &[Right, Forward]);
+
+        assert_eq!(commands, Ok(vec![Forward, Forward, Forward]));
+        // and moving west would be the end of the robot
+        assert!(mission
+            .plan_route(robot(0, 0, West), target, &[Forward])
+            .is_err());
+        assert_eq!(mission.scents().len(), 1);
+    }
+}
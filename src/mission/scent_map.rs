@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::robot::Rover;
+
+// Dense maps take a byte for every cell of the world, so larger worlds use sparse maps
+const DENSE_LIMIT: usize = 1 << 24;
+
+// The scents left in a world. Worlds which are small and simple enough keep a byte for
+// each cell in a flat array, with a bit for each heading robots were lost towards from
+// it. Any other world keeps the headings for each scented cell in a map.
+pub enum ScentMap<R: Rover> {
+    Dense {
+        cells: Vec<u8>,
+        scents: Vec<(R::Cell, R::Heading)>, // the same scents again, to list them
+    },
+    Sparse(HashMap<R::Cell, HashSet<R::Heading>>),
+}
+
+impl<R: Rover> ScentMap<R> {
+    pub fn new(world: &R::World) -> ScentMap<R> {
+        match R::scent_cells(world) {
+            Some(cells) if cells <= DENSE_LIMIT => ScentMap::Dense {
+                cells: vec![0; cells],
+                scents: Vec::new(),
+            },
+            _ => ScentMap::sparse(),
+        }
+    }
+
+    pub fn sparse() -> ScentMap<R> {
+        ScentMap::Sparse(HashMap::new())
+    }
+
+    pub fn contains(&self, world: &R::World, cell: R::Cell, heading: R::Heading) -> bool {
+        match self {
+            ScentMap::Dense { cells, scents } => match R::scent_slot(world, cell, heading) {
+                Some((index, bit)) => cells[index] & bit != 0,
+                // scents outside the world are only ever loaded from elsewhere
+                None => scents.contains(&(cell, heading)),
+            },
+            ScentMap::Sparse(map) => map.get(&cell).is_some_and(|h| h.contains(&heading)),
+        }
+    }
+
+    pub fn insert(&mut self, world: &R::World, cell: R::Cell, heading: R::Heading) {
+        match self {
+            ScentMap::Dense { cells, scents } => match R::scent_slot(world, cell, heading) {
+                Some((index, bit)) if cells[index] & bit == 0 => {
+                    cells[index] |= bit;
+                    scents.push((cell, heading));
+                }
+                Some(_) => (),
+                None if !scents.contains(&(cell, heading)) => scents.push((cell, heading)),
+                None => (),
+            },
+            ScentMap::Sparse(map) => {
+                map.entry(cell).or_default().insert(heading);
+            }
+        }
+    }
+
+    // All the scents, in no particular order
+    pub fn to_vec(&self) -> Vec<(R::Cell, R::Heading)> {
+        match self {
+            ScentMap::Dense { scents, .. } => scents.clone(),
+            ScentMap::Sparse(map) => map
+                .iter()
+                .flat_map(|(&cell, headings)| headings.iter().map(move |&heading| (cell, heading)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::location::Point;
+    use crate::geo::orientation::Orientation;
+    use crate::geo::world::World;
+    use crate::robot::Robot;
+
+    #[test]
+    fn dense_and_sparse_maps_agree() {
+        let world = World::between(Point { x: -2, y: -1 }, Point { x: 3, y: 4 });
+        let scents = vec![
+            (Point { x: -2, y: 4 }, Orientation::North),
+            (Point { x: -2, y: 4 }, Orientation::NorthWest),
+            (Point { x: 3, y: -1 }, Orientation::East),
+            (Point { x: 9, y: 9 }, Orientation::South), // outside the world
+        ];
+
+        let mut dense = ScentMap::<Robot>::new(&world);
+        let mut sparse = ScentMap::<Robot>::sparse();
+        assert!(matches!(dense, ScentMap::Dense { .. }));
+
+        for &(cell, heading) in scents.iter().chain(scents.iter()) {
+            dense.insert(&world, cell, heading);
+            sparse.insert(&world, cell, heading);
+        }
+
+        for &(cell, heading) in &scents {
+            assert!(dense.contains(&world, cell, heading));
+            assert!(sparse.contains(&world, cell, heading));
+        }
+        assert!(!dense.contains(&world, Point { x: -2, y: 4 }, Orientation::West));
+
+        let (mut dense, mut sparse) = (dense.to_vec(), sparse.to_vec());
+        dense.sort();
+        sparse.sort();
+        assert_eq!(dense, sparse);
+        assert_eq!(dense.len(), 4);
+    }
+
+    #[test]
+    fn huge_worlds_are_sparse() {
+        let world = World::rectangle(Point {
+            x: 100_000,
+            y: 100_000,
+        });
+
+        assert!(matches!(
+            ScentMap::<Robot>::new(&world),
+            ScentMap::Sparse(_)
+        ));
+    }
+}
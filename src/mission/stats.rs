@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use super::{Outcome, Step};
+use crate::robot::{Robot, Rover};
+
+// Figures about the robots dispatched on a mission so far, e.g. to compare how hard
+// plans are, or to check a generator's settings give sensible missions
+#[derive(PartialEq, Clone, Debug)]
+pub struct Stats<R: Rover = Robot> {
+    pub robots: usize,
+    pub lost: usize,
+    pub collided: usize,
+    pub scents: usize,   // laid by robots lost on this mission
+    pub scented: usize,  // moves which would have lost a robot, but for a scent
+    pub executed: usize, // commands carried out, including turns on the spot
+    pub ignored: usize,  // commands skipped for a scent, an obstacle or an edge
+    // moves from one cell to another, by all robots and by the robot which went furthest
+    pub total_path: usize,
+    pub longest_path: usize,
+    pub losses: HashMap<R::Edge, usize>, // by the edge of the world robots were lost over
+}
+
+impl<R: Rover> Default for Stats<R> {
+    fn default() -> Stats<R> {
+        Stats {
+            robots: 0,
+            lost: 0,
+            collided: 0,
+            scents: 0,
+            scented: 0,
+            executed: 0,
+            ignored: 0,
+            total_path: 0,
+            longest_path: 0,
+            losses: HashMap::new(),
+        }
+    }
+}
+
+impl<R: Rover> Stats<R> {
+    // The share of robots lost, from 0 to 1
+    pub fn loss_rate(&self) -> f64 {
+        match self.robots {
+            0 => 0.0,
+            robots => self.lost as f64 / robots as f64,
+        }
+    }
+
+    pub fn average_path(&self) -> f64 {
+        match self.robots {
+            0 => 0.0,
+            robots => self.total_path as f64 / robots as f64,
+        }
+    }
+
+    // Counts a robot's step, returning whether it moved to another cell
+    pub(super) fn step(&mut self, before: R, step: Step<R>) -> bool {
+        match step {
+            Step::Moved(robot) => {
+                self.executed += 1;
+                return robot.cell() != before.cell();
+            }
+            Step::Scented(_) => {
+                self.scented += 1;
+                self.ignored += 1;
+            }
+            Step::Blocked(_) => self.ignored += 1,
+            Step::Collided(_) | Step::Lost(_) => self.executed += 1,
+        }
+        false
+    }
+
+    pub(super) fn loss(&mut self, edge: R::Edge) {
+        self.scents += 1;
+        *self.losses.entry(edge).or_default() += 1;
+    }
+
+    // Counts a robot once its run is over, having moved along a path so long
+    pub(super) fn outcome(&mut self, outcome: Outcome<R>, path: usize) {
+        self.robots += 1;
+        self.total_path += path;
+        self.longest_path = self.longest_path.max(path);
+
+        match outcome {
+            Outcome::Lost(_) => self.lost += 1,
+            Outcome::Collided(_) => self.collided += 1,
+            Outcome::Success(_) => (),
+        }
+    }
+}
@@ -1,34 +1,132 @@
-use std::io::Read;
+use std::io::{self, Read};
 use std::str;
 
-use combine::error::ParseError;
+use combine::error::{ParseError, StreamError};
 use combine::parser::byte::{bytes, digit, space, spaces};
 use combine::stream::buffered;
 use combine::stream::position;
 use combine::stream::read;
-use combine::stream::Stream;
-use combine::{eof, many1, one_of, optional, skip_many, EasyParser, Parser};
+use combine::stream::{Positioned, Stream, StreamErrorFor};
+use combine::{
+    choice, count_min_max, eof, look_ahead, many, many1, none_of, one_of, optional, parser,
+    skip_many, skip_many1, token, EasyParser, Parser,
+};
 
-use crate::geo::location::Point;
+use crate::commands::CommandSet;
+use crate::drone::Drone;
+use crate::geo::grid::Grid;
+use crate::geo::location::{Coord, Point};
+use crate::geo::mask::Mask;
 use crate::geo::orientation::Orientation;
+use crate::geo::space::{Pitch, Point3, Volume};
+use crate::geo::world::{Shape, World};
 use crate::mission::{Mission, Outcome};
 use crate::robot::{Command, Robot};
+use crate::scents::Scent;
+
+// A drone and its instructions, as read from a flight plan
+type Flight = Result<(Drone, Vec<Command>), String>;
+
+// The most commands a robot's line can expand to, so a short line with large repeat
+// counts can't run out of memory
+const MAX_COMMANDS: usize = 1 << 20;
+
+// Commands expanded from repeats, which stop being collected once there are too many
+#[derive(Default)]
+struct Expanded {
+    commands: Vec<Command>,
+    overflowed: bool,
+}
+
+impl Extend<Vec<Command>> for Expanded {
+    fn extend<T: IntoIterator<Item = Vec<Command>>>(&mut self, iter: T) {
+        for commands in iter {
+            if self.overflowed || self.commands.len() + commands.len() > MAX_COMMANDS {
+                self.overflowed = true;
+            } else {
+                self.commands.extend(commands);
+            }
+        }
+    }
+}
+
+impl Expanded {
+    // The commands, or nothing if they'd be more than MAX_COMMANDS when repeated n times
+    fn repeated(self, n: usize) -> Option<Vec<Command>> {
+        match self.commands.len().checked_mul(n) {
+            Some(len) if !self.overflowed && len <= MAX_COMMANDS => Some(self.commands.repeat(n)),
+            _ => None,
+        }
+    }
+}
+
+type InputStream<'a, R> =
+    Box<buffered::Stream<position::Stream<read::Stream<&'a mut R>, position::IndexPositioner>>>;
+
+// A plan for either robots on a grid or drones in a volume, depending on its header.
+//
+// An input can hold several plans, each starting with a line like '=== Day 2', which
+// names the mission.
+pub enum Plan<'a, R>
+where
+    R: Read,
+{
+    Surface(MissionPlan<'a, R>),
+    Flight(FlightPlan<'a, R>),
+}
 
 pub struct MissionPlan<'a, R>
 where
     R: Read,
 {
-    upper_right: Point,
-    stream:
-        Box<buffered::Stream<position::Stream<read::Stream<&'a mut R>, position::IndexPositioner>>>,
+    pub world: World,
+    pub name: Option<String>, // given on the line starting the plan, if there was one
+    index: usize,             // of the plan in its input
+    done: bool,
+    commands: CommandSet,
+    stream: InputStream<'a, R>,
+}
+
+pub struct FlightPlan<'a, R>
+where
+    R: Read,
+{
+    pub volume: Volume,
+    pub name: Option<String>,
+    index: usize,
+    done: bool,
+    stream: InputStream<'a, R>,
 }
 
 pub struct MissionOutcomes<'a, R>
 where
     R: Read,
 {
-    stream:
-        Box<buffered::Stream<position::Stream<read::Stream<&'a mut R>, position::IndexPositioner>>>,
+    stream: InputStream<'a, R>,
+}
+
+// The first line of a plan
+#[derive(PartialEq, Debug)]
+enum Header {
+    World(World),
+    Volume(Volume),
+}
+
+// Parses an integer, which has to fit a coordinate
+fn number<Input>() -> impl Parser<Input, Output = Coord>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (optional(token(b'-')), many1(digit())).and_then(|(sign, digits): (Option<u8>, Vec<u8>)| {
+        let n = sign
+            .into_iter()
+            .chain(digits)
+            .map(char::from)
+            .collect::<String>();
+        n.parse()
+            .map_err(|_| StreamErrorFor::<Input>::message_static_message("number out of range"))
+    })
 }
 
 // Parses an X, Y point written as two integers separated by whitespace
@@ -37,47 +135,248 @@ where
     Input: Stream<Token = u8>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    (many1(digit()), spaces(), many1(digit())).map(|(x, _, y): (Vec<u8>, _, Vec<u8>)| Point {
-        x: str::from_utf8(&x).unwrap().parse().unwrap(),
-        y: str::from_utf8(&y).unwrap().parse().unwrap(),
-    })
+    (number(), spaces(), number()).map(|(x, _, y)| Point { x, y })
+}
+
+// Parses an X, Y, Z point written as three integers separated by whitespace
+fn point3<Input>() -> impl Parser<Input, Output = Point3>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (number(), spaces(), number(), spaces(), number()).map(|(x, _, y, _, z)| Point3 { x, y, z })
 }
 
-// Parses an orientation written as a single letter N, E, S or W
+// Parses the cells of a world, either as ASCII art of a given width and height, e.g.
+// 'MASK 3 2' followed by rows like '#.#', or as a list of rectangular regions, e.g.
+// 'REGIONS 2' followed by the lower left and upper right corners of each, like '0 0 2 0'
+fn mask<Input>() -> impl Parser<Input, Output = Mask>
+where
+    Input: Stream<Token = u8, Range = &'static [u8]>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let art = bytes(&b"MASK"[..])
+        .with(spaces())
+        .with((number(), spaces(), number()))
+        .and_then(|(width, _, height): (Coord, _, Coord)| {
+            match width.max(0) as u128 * height.max(0) as u128 {
+                cells if cells <= Mask::MAX_CELLS => Ok((width, (), height)),
+                _ => Err(StreamErrorFor::<Input>::message_static_message(
+                    "mask too large",
+                )),
+            }
+        })
+        .then(|(width, _, height)| {
+            let row = count_min_max(
+                width.max(0) as usize,
+                width.max(0) as usize,
+                one_of("#.".bytes()),
+            );
+            count_min_max(
+                height.max(0) as usize,
+                height.max(0) as usize,
+                spaces().with(row),
+            )
+        })
+        .map(|rows: Vec<Vec<u8>>| {
+            let rows: Vec<String> = rows
+                .into_iter()
+                .map(|row| row.into_iter().map(char::from).collect())
+                .collect();
+            Mask::from_art(&rows)
+        });
+
+    let regions = bytes(&b"REGIONS"[..])
+        .with(spaces())
+        .with(number())
+        .and_then(|n: Coord| match n {
+            n if (n as i128) <= Mask::MAX_CELLS as i128 => Ok(n),
+            _ => Err(StreamErrorFor::<Input>::message_static_message(
+                "too many regions",
+            )),
+        })
+        .then(|n| {
+            let region = (spaces(), point(), spaces(), point())
+                .map(|(_, lower_left, _, upper_right)| (lower_left, upper_right));
+            count_min_max(n.max(0) as usize, n.max(0) as usize, region)
+        })
+        .and_then(|regions: Vec<(Point, Point)>| {
+            let mut mask = Mask::new();
+            for (lower_left, upper_right) in regions {
+                let cells = Mask::region_len(lower_left, upper_right);
+                if mask.len() as u128 + cells > Mask::MAX_CELLS {
+                    return Err(StreamErrorFor::<Input>::message_static_message(
+                        "mask too large",
+                    ));
+                }
+                mask.insert_region(lower_left, upper_right);
+            }
+            Ok(mask)
+        });
+
+    art.or(regions)
+}
+
+// Parses the world a mission takes place in: the upper right corner of a square grid,
+// e.g. '5 3', or its lower left and upper right corners, e.g. '-2 -1 5 3', the same for
+// a rectangular hex grid, e.g. '5 3 HEX', the radius of a hexagonal hex grid, e.g.
+// 'HEX 3', the upper corner of a volume, e.g. '5 3 4', or a mask (see above)
+fn header<Input>() -> impl Parser<Input, Output = Header>
+where
+    Input: Stream<Token = u8, Range = &'static [u8]>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let blanks = || skip_many(one_of(" \t".bytes())); // whitespace on the same line
+
+    choice((
+        mask().map(|mask| {
+            Header::World(World {
+                grid: Grid::Square,
+                shape: Shape::Mask(mask),
+            })
+        }),
+        bytes(&b"HEX"[..])
+            .with(spaces())
+            .with(number())
+            .map(|radius| {
+                Header::World(World {
+                    grid: Grid::Hex,
+                    shape: Shape::Hexagon(radius),
+                })
+            }),
+        (
+            point().skip(blanks()),
+            optional(number().skip(blanks())),
+            optional(number().skip(blanks())),
+            optional(bytes(&b"HEX"[..])),
+        )
+            .and_then(|(first, third, fourth, hex)| {
+                let grid = hex.map_or(Grid::Square, |_| Grid::Hex);
+                let world = |lower_left, upper_right| {
+                    Header::World(World {
+                        grid,
+                        shape: Shape::Rectangle(lower_left, upper_right),
+                    })
+                };
+
+                match (third, fourth, grid) {
+                    (None, _, _) => Ok(world(World::ORIGIN, first)),
+                    (Some(x), Some(y), _) => Ok(world(first, Point { x, y })),
+                    (Some(z), None, Grid::Square) => Ok(Header::Volume(Volume {
+                        upper: Point3 {
+                            x: first.x,
+                            y: first.y,
+                            z,
+                        },
+                    })),
+                    (Some(_), None, Grid::Hex) => Err(
+                        StreamErrorFor::<Input>::message_static_message("volumes can't be hex"),
+                    ),
+                }
+            }),
+    ))
+}
+
+// Parses a line starting a plan, e.g. '===' or '=== Day 2', with an optional name
+fn delimiter<Input>() -> impl Parser<Input, Output = Option<String>>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    skip_many1(token(b'='))
+        .with(many(none_of("\r\n".bytes())))
+        .map(|name: Vec<u8>| {
+            let name = String::from_utf8_lossy(&name).trim().to_string();
+            Some(name).filter(|name| !name.is_empty())
+        })
+}
+
+// Parses an orientation written as N, E, S or W, or one of NE, SE, SW and NW
 fn orientation<Input>() -> impl Parser<Input, Output = Orientation>
 where
     Input: Stream<Token = u8>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    one_of("NESW".bytes()).map(|token: u8| match token as char {
-        'N' => Orientation::North,
-        'E' => Orientation::East,
-        'S' => Orientation::South,
-        'W' => Orientation::West,
-        _ => panic!(),
+    (one_of("NESW".bytes()), optional(one_of("EW".bytes()))).and_then(
+        |(first, second): (u8, Option<u8>)| match (first as char, second.map(|t| t as char)) {
+            ('N', None) => Ok(Orientation::North),
+            ('E', None) => Ok(Orientation::East),
+            ('S', None) => Ok(Orientation::South),
+            ('W', None) => Ok(Orientation::West),
+            ('N', Some('E')) => Ok(Orientation::NorthEast),
+            ('S', Some('E')) => Ok(Orientation::SouthEast),
+            ('S', Some('W')) => Ok(Orientation::SouthWest),
+            ('N', Some('W')) => Ok(Orientation::NorthWest),
+            _ => Err(StreamErrorFor::<Input>::unexpected_static_message(
+                "orientation",
+            )),
+        },
+    )
+}
+
+// Parses a single command, either a built-in L, R, F, B, U, l or r, or a custom one
+fn command<Input>(commands: CommandSet) -> impl Parser<Input, Output = Command>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    one_of(commands.letters()).map(move |cmd: u8| commands.command(cmd as char).unwrap())
+}
+
+// Parses a repeat count
+fn count<Input>() -> impl Parser<Input, Output = usize>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    many1(digit()).and_then(|n: Vec<u8>| {
+        str::from_utf8(&n)
+            .unwrap()
+            .parse()
+            .map_err(|_| StreamErrorFor::<Input>::message_static_message("count out of range"))
     })
 }
 
-// Parses a contiguous series of commands L, R or F
-fn commands<Input>() -> impl Parser<Input, Output = Vec<Command>>
+parser! {
+    // Parses a command with an optional repeat count, e.g. '3F', or a repeated group
+    // of commands, e.g. '(FL)4', expanded into the individual commands
+    fn repeat[Input](commands: CommandSet)(Input) -> Vec<Command>
+    where [
+        Input: Stream<Token = u8>,
+        Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+    ]
+    {
+        let too_many = || StreamErrorFor::<Input>::message_static_message("too many commands");
+
+        choice((
+            (count(), command(commands.clone())).and_then(move |(n, cmd)| match n {
+                n if n <= MAX_COMMANDS => Ok(vec![cmd; n]),
+                _ => Err(too_many()),
+            }),
+            command(commands.clone()).map(|cmd| vec![cmd]),
+            (token(b'('), many1(repeat(commands.clone())), token(b')'), optional(count())).and_then(
+                move |(_, cmds, _, n): (_, Expanded, _, _)| {
+                    cmds.repeated(n.unwrap_or(1)).ok_or_else(too_many)
+                },
+            ),
+        ))
+    }
+}
+
+// Parses a contiguous series of commands, possibly repeated
+fn commands<Input>(commands: CommandSet) -> impl Parser<Input, Output = Vec<Command>>
 where
     Input: Stream<Token = u8>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    many1(one_of("LRF".bytes())).map(|cmds: Vec<u8>| {
-        cmds.iter()
-            .map(|cmd| match *cmd as char {
-                'L' => Command::Left,
-                'R' => Command::Right,
-                'F' => Command::Forward,
-                _ => panic!(),
-            })
-            .collect()
+    many1(repeat(commands)).and_then(|cmds: Expanded| {
+        cmds.repeated(1)
+            .ok_or_else(|| StreamErrorFor::<Input>::message_static_message("too many commands"))
     })
 }
 
 // Parses a robot definition followed by instructions
-fn robot<Input>() -> impl Parser<Input, Output = (Robot, Vec<Command>)>
+fn robot<Input>(commands: CommandSet) -> impl Parser<Input, Output = (Robot, Vec<Command>)>
 where
     Input: Stream<Token = u8>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
@@ -86,7 +385,7 @@ where
         .skip(spaces())
         .and(orientation())
         .skip(spaces()) // spaces covers new lines
-        .and(commands())
+        .and(self::commands(commands))
         .skip(space())
         .map(|((point, orientation), commands)| {
             (
@@ -99,7 +398,45 @@ where
         })
 }
 
-// Parses an outcome of a robot run, e.g. '3 3 N', or '5 2 E LOST'
+// Parses a pitch written straight after a drone's heading, '+' for up and '-' for down
+fn pitch<Input>() -> impl Parser<Input, Output = Pitch>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    optional(one_of("+-".bytes())).map(|pitch| match pitch {
+        Some(b'+') => Pitch::Up,
+        Some(_) => Pitch::Down,
+        None => Pitch::Level,
+    })
+}
+
+// Parses a drone definition followed by instructions, e.g. '1 2 0 N' or '1 2 3 E+'
+fn drone<Input>() -> impl Parser<Input, Output = (Drone, Vec<Command>)>
+where
+    Input: Stream<Token = u8>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    point3()
+        .skip(spaces())
+        .and(orientation())
+        .and(pitch())
+        .skip(spaces())
+        .and(self::commands(CommandSet::new()))
+        .skip(space())
+        .map(|(((position, facing), pitch), commands)| {
+            (
+                Drone {
+                    position,
+                    facing,
+                    pitch,
+                },
+                commands,
+            )
+        })
+}
+
+// Parses an outcome of a robot run, e.g. '3 3 N', '5 2 E LOST' or '1 0 S COLLIDED'
 fn outcome<Input>() -> impl Parser<Input, Output = Outcome>
 where
     Input: Stream<Token = u8, Range = &'static [u8]>,
@@ -109,51 +446,200 @@ where
         .skip(spaces())
         .and(orientation())
         .skip(spaces())
-        .and(optional(bytes(&b"LOST"[..])).skip(spaces()))
-        .map(|((position, orientation), lost)| match lost {
-            None => Outcome::Success(Robot {
-                position,
-                facing: orientation,
-            }),
-            Some(_) => Outcome::Lost(Robot {
+        .and(optional(bytes(&b"LOST"[..]).or(bytes(&b"COLLIDED"[..]))).skip(spaces()))
+        .map(|((position, orientation), status)| {
+            let robot = Robot {
                 position,
                 facing: orientation,
-            }),
+            };
+
+            match status {
+                None => Outcome::Success(robot),
+                Some(b"LOST") => Outcome::Lost(robot),
+                Some(_) => Outcome::Collided(robot),
+            }
         })
 }
 
-impl<R> MissionPlan<'_, R>
+// Reads the header of a plan, leaving the stream at the first robot
+fn read_plan<R: Read>(mut stream: InputStream<'_, R>, index: usize) -> Result<Plan<'_, R>, String> {
+    let parsed = skip_many(space())
+        .with(optional(delimiter().skip(spaces())))
+        .and(self::header())
+        .skip(spaces())
+        .easy_parse(stream.as_mut());
+
+    let (name, header) = match parsed {
+        Ok((plan, _)) => plan,
+        Err(error) => {
+            let human_error = error
+                .map_token(|t| t as char)
+                .map_range(|r| std::str::from_utf8(r).unwrap());
+            return Err(format!("Expected grid size. {}", human_error));
+        }
+    };
+    let name = name.map(|name| name.unwrap_or_else(|| format!("Mission {}", index + 1)));
+
+    Ok(match header {
+        Header::World(world) => Plan::Surface(MissionPlan {
+            world,
+            name,
+            index,
+            done: false,
+            commands: CommandSet::new(),
+            stream,
+        }),
+        Header::Volume(volume) => Plan::Flight(FlightPlan {
+            volume,
+            name,
+            index,
+            done: false,
+            stream,
+        }),
+    })
+}
+
+// Reads the plan after the one which ended at the stream's position, if there is one.
+// Anything up to the line starting the next plan is skipped, e.g. after a malformed robot.
+fn read_next_plan<R: Read>(
+    mut stream: InputStream<'_, R>,
+    index: usize,
+) -> Option<Result<Plan<'_, R>, String>> {
+    let next = skip_many(none_of("=".bytes()))
+        .with(optional(look_ahead(token(b'='))))
+        .parse(stream.as_mut());
+
+    match next {
+        Ok((Some(_), _)) => Some(read_plan(stream, index)),
+        _ => None,
+    }
+}
+
+// Reads scents written one per line, e.g. '3 3 N'
+pub fn read_scents(text: &str) -> Result<Vec<Scent>, String> {
+    let scent = point()
+        .skip(spaces())
+        .and(orientation())
+        .skip(spaces())
+        .map(|(position, heading)| Scent { position, heading });
+
+    let scents = spaces()
+        .with(many(scent))
+        .skip(eof())
+        .easy_parse(position::Stream::new(text.as_bytes()));
+
+    match scents {
+        Ok((scents, _)) => Ok(scents),
+        Err(error) => {
+            let human_error = error
+                .map_token(|t| t as char)
+                .map_range(|r| std::str::from_utf8(r).unwrap());
+            Err(format!("{}", human_error))
+        }
+    }
+}
+
+// Skips input up to an offset recorded earlier, so reading carries on from there. Positions
+// in error messages still count from the start of the input.
+fn skip_to<R: Read>(input: &mut R, offset: u64) -> Result<InputStream<'_, R>, String> {
+    let skipped = io::copy(&mut (&mut *input).take(offset), &mut io::sink())
+        .map_err(|e| format!("Can't skip to {}: {}", offset, e))?;
+    if skipped < offset {
+        return Err(format!("Input ends at {}, before {}", skipped, offset));
+    }
+
+    let positioner = position::IndexPositioner::new_with_position(offset as usize);
+    let stream = position::Stream::with_positioner(read::Stream::new(input), positioner);
+    Ok(Box::new(buffered::Stream::new(stream, 1)))
+}
+
+impl<R> Plan<'_, R>
 where
     R: Read,
 {
-    pub fn read(input: &mut R) -> Result<MissionPlan<R>, String> {
-        // Should return Result
-        let mut stream = buffered::Stream::new(position::Stream::new(read::Stream::new(input)), 1);
-        let upper_right;
-
-        {
-            let point = skip_many(space())
-                .and(point())
-                .skip(spaces())
-                .parse(&mut stream);
-
-            upper_right = match point {
-                Ok(((_, point), _)) => point,
-                Err(err) => return Err(format!("Expected grid size. {}", err)), // this could be improved
-            };
-        } // return borrowed stream
+    pub fn read(input: &mut R) -> Result<Plan<'_, R>, String> {
+        let stream = buffered::Stream::new(position::Stream::new(read::Stream::new(input)), 1);
+        read_plan(Box::new(stream), 0)
+    }
+}
+
+impl<'a, R> MissionPlan<'a, R>
+where
+    R: Read,
+{
+    pub fn read(input: &mut R) -> Result<MissionPlan<'_, R>, String> {
+        match Plan::read(input)? {
+            Plan::Surface(plan) => Ok(plan),
+            Plan::Flight(plan) => Err(format!("Expected a grid, found a volume {}", plan.volume)),
+        }
+    }
 
+    // Carries on reading a plan for a given world from an offset, skipping everything
+    // before it, including the header
+    pub fn resume(input: &mut R, world: World, offset: u64) -> Result<MissionPlan<'_, R>, String> {
         Ok(MissionPlan {
-            upper_right: upper_right,
-            stream: Box::new(stream), // ...so it can be moved here
+            world,
+            name: None,
+            index: 0,
+            done: false,
+            commands: CommandSet::new(),
+            stream: skip_to(input, offset)?,
         })
     }
 
+    // How far into the input the plan has been read, in bytes
+    pub fn offset(&self) -> u64 {
+        self.stream.position() as u64
+    }
+
+    // Reads the next plan in the input, skipping any robots of this one not read yet
+    pub fn next_plan(mut self) -> Option<Result<Plan<'a, R>, String>> {
+        while let Some(Ok(_)) = self.next() {}
+        read_next_plan(self.stream, self.index + 1)
+    }
+
+    // Accepts custom commands in the plan, alongside the built-in ones
+    pub fn with_commands(self, commands: CommandSet) -> MissionPlan<'a, R> {
+        MissionPlan { commands, ..self }
+    }
+
     pub fn mission(self) -> Mission<Self, Result<(Robot, Vec<Command>), String>> {
-        Mission::new(self.upper_right, self)
+        let commands = self.commands.clone();
+        Mission::in_world(self.world.clone(), self).with_commands(commands)
     }
 }
 
+// Checks a robot's heading and commands are available on a world's grid, as they are for
+// every robot read from a plan
+pub fn validate(
+    world: &World,
+    robot: Robot,
+    commands: Vec<Command>,
+) -> Result<(Robot, Vec<Command>), String> {
+    let pitch = |c: &Command| matches!(c, Command::PitchUp | Command::PitchDown);
+    if commands.iter().any(pitch) {
+        return Err("Pitching up and down is only available in a volume".to_string());
+    }
+
+    if world.grid != Grid::Hex {
+        return Ok((robot, commands));
+    }
+
+    if !world.grid.headings().contains(&robot.facing) {
+        return Err(format!(
+            "Heading {} is not available on a hex grid",
+            robot.facing
+        ));
+    }
+
+    let half_turn = |c: &Command| matches!(c, Command::HalfLeft | Command::HalfRight);
+    if commands.iter().any(half_turn) {
+        return Err("Half turns are not available on a hex grid".to_string());
+    }
+
+    Ok((robot, commands))
+}
+
 impl<R> Iterator for MissionPlan<'_, R>
 where
     R: Read,
@@ -161,17 +647,86 @@ where
     type Item = Result<(Robot, Vec<Command>), String>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
         let stream = self.stream.as_mut();
         let robot = skip_many(space())
-            .and(
-                robot().map(|r| Some(r)).or(eof().map(|()| None)), // an expected end of input
-            )
+            .and(choice((
+                robot(self.commands.clone()).map(Some),
+                look_ahead(token(b'=')).map(|_| None), // the start of the next plan
+                eof().map(|()| None),                  // an expected end of input
+            )))
             .easy_parse(stream);
 
         match robot {
-            Ok(((_, None), _)) => None,
-            Ok(((_, Some(robot)), _)) => Some(Ok(robot)),
+            Ok(((_, None), _)) => {
+                self.done = true;
+                None
+            }
+            Ok(((_, Some((robot, commands))), _)) => Some(validate(&self.world, robot, commands)),
             Err(error) => {
+                self.done = true;
+                let human_error = error
+                    .map_token(|t| t as char)
+                    .map_range(|r| std::str::from_utf8(r).unwrap());
+                Some(Err(format!("{}", human_error)))
+            }
+        }
+    }
+}
+
+impl<'a, R> FlightPlan<'a, R>
+where
+    R: Read,
+{
+    pub fn read(input: &mut R) -> Result<FlightPlan<'_, R>, String> {
+        match Plan::read(input)? {
+            Plan::Flight(plan) => Ok(plan),
+            Plan::Surface(plan) => Err(format!("Expected a volume, found a grid {}", plan.world)),
+        }
+    }
+
+    pub fn mission(self) -> Mission<Self, Flight, Drone> {
+        Mission::in_world(self.volume.clone(), self)
+    }
+
+    // Reads the next plan in the input, skipping any drones of this one not read yet
+    pub fn next_plan(mut self) -> Option<Result<Plan<'a, R>, String>> {
+        while let Some(Ok(_)) = self.next() {}
+        read_next_plan(self.stream, self.index + 1)
+    }
+}
+
+impl<R> Iterator for FlightPlan<'_, R>
+where
+    R: Read,
+{
+    type Item = Flight;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let stream = self.stream.as_mut();
+        let drone = skip_many(space())
+            .and(choice((
+                drone().map(Some),
+                look_ahead(token(b'=')).map(|_| None), // the start of the next plan
+                eof().map(|()| None),                  // an expected end of input
+            )))
+            .easy_parse(stream);
+
+        match drone {
+            Ok(((_, None), _)) => {
+                self.done = true;
+                None
+            }
+            Ok(((_, Some(drone)), _)) => Some(Ok(drone)),
+            Err(error) => {
+                self.done = true;
                 let human_error = error
                     .map_token(|t| t as char)
                     .map_range(|r| std::str::from_utf8(r).unwrap());
@@ -185,7 +740,7 @@ impl<R> MissionOutcomes<'_, R>
 where
     R: Read,
 {
-    pub fn read(input: &mut R) -> MissionOutcomes<R> {
+    pub fn read(input: &mut R) -> MissionOutcomes<'_, R> {
         // Should return Result
         let stream = buffered::Stream::new(position::Stream::new(read::Stream::new(input)), 1);
 
@@ -193,6 +748,17 @@ where
             stream: Box::new(stream),
         }
     }
+
+    pub fn resume(input: &mut R, offset: u64) -> Result<MissionOutcomes<'_, R>, String> {
+        Ok(MissionOutcomes {
+            stream: skip_to(input, offset)?,
+        })
+    }
+
+    // How far into the input the outcomes have been read, in bytes
+    pub fn offset(&self) -> u64 {
+        self.stream.position() as u64
+    }
 }
 
 impl<R> Iterator for MissionOutcomes<'_, R>
@@ -203,9 +769,10 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         let stream = self.stream.as_mut();
-        let outcome = skip_many(space())
+        // outcomes of several missions are labelled as in the plan, e.g. '=== Day 2'
+        let outcome = skip_many(space().map(|_| ()).or(delimiter().map(|_| ())))
             .and(
-                outcome().map(|r| Some(r)).or(eof().map(|()| None)), // expected EOF
+                outcome().map(Some).or(eof().map(|()| None)), // expected EOF
             )
             .easy_parse(stream);
 
@@ -229,12 +796,11 @@ where
 mod tests {
     use super::*;
 
-    use combine;
     use combine::stream::position;
     use std::io::Cursor;
 
     use crate::geo::location::Point;
-    use crate::robot::Command::{Forward as F, Left as L, Right as R};
+    use crate::robot::Command::{Backward as B, Forward as F, Left as L, Right as R, UTurn as U};
 
     #[test]
     fn recognises_a_simple_point() {
@@ -263,19 +829,112 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn recognises_orientations() {
+        let cases: [(&[u8], Orientation); 4] = [
+            (b"N", Orientation::North),
+            (b"NE", Orientation::NorthEast),
+            (b"SW", Orientation::SouthWest),
+            (b"W", Orientation::West),
+        ];
+
+        for &(input, expected) in &cases {
+            let (actual, _) = orientation().parse(position::Stream::new(input)).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn does_not_recognise_a_mixed_up_orientation() {
+        let input = b"EW";
+        let actual = orientation().parse(position::Stream::new(&input[..]));
+
+        assert!(actual.is_err());
+    }
+
     #[test]
     fn recognises_commands() {
         let input = b"LRFFLFR";
-        let (actual, _) = commands().parse(position::Stream::new(&input[..])).unwrap();
+        let (actual, _) = commands(CommandSet::new())
+            .parse(position::Stream::new(&input[..]))
+            .unwrap();
         let expected = vec![L, R, F, F, L, F, R];
 
         assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn recognises_extended_commands() {
+        let input = b"FBULlr";
+        let (actual, _) = commands(CommandSet::new())
+            .parse(position::Stream::new(&input[..]))
+            .unwrap();
+        let expected = vec![F, B, U, L, Command::HalfLeft, Command::HalfRight];
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn expands_repeated_commands() {
+        let input = b"3FL(FR)2(B(UF)2)";
+        let (actual, _) = commands(CommandSet::new())
+            .parse(position::Stream::new(&input[..]))
+            .unwrap();
+        let expected = vec![F, F, F, L, F, R, F, R, B, U, F, U, F];
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn refuses_to_expand_too_many_commands() {
+        for input in [&b"4000000000F"[..], b"(((F)1000)1000)1000", b"(F)5000000"] {
+            let actual = commands(CommandSet::new()).parse(position::Stream::new(input));
+
+            assert!(actual.is_err());
+        }
+
+        let mut input = Cursor::new("5 3\n1 1 E\n4000000000F\n");
+        let mut plan = MissionPlan::read(&mut input).unwrap();
+        assert!(plan.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn does_not_recognise_an_unclosed_group() {
+        let input = b"(FL";
+        let actual = commands(CommandSet::new()).parse(position::Stream::new(&input[..]));
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn recognises_custom_commands() {
+        struct Wait;
+
+        impl crate::commands::CustomCommand for Wait {
+            fn apply(&self, robot: Robot) -> Robot {
+                robot
+            }
+        }
+
+        let mut custom = CommandSet::new();
+        custom.register('W', Wait).unwrap();
+
+        let input = b"FW(WL)2";
+        let (actual, _) = commands(custom)
+            .parse(position::Stream::new(&input[..]))
+            .unwrap();
+        let w = Command::Custom('W');
+        let expected = vec![F, w, w, L, w, L];
+
+        assert_eq!(actual, expected)
+    }
+
     #[test]
     fn recognises_a_robot() {
         let input = b"4  5  W\n\r  LRFFLFR\n"; // we don't discriminate against whitespace
-        let (actual, _) = robot().parse(position::Stream::new(&input[..])).unwrap();
+        let (actual, _) = robot(CommandSet::new())
+            .parse(position::Stream::new(&input[..]))
+            .unwrap();
         let expected = (
             Robot {
                 position: Point { x: 4, y: 5 },
@@ -314,16 +973,227 @@ mod tests {
         }
     }
 
+    #[test]
+    fn recognises_a_collision_outcome() {
+        let input = b"1 0 S COLLIDED\n";
+        let (actual, _) = outcome().parse(position::Stream::new(&input[..])).unwrap();
+        let expected = Outcome::Collided(Robot {
+            position: Point { x: 1, y: 0 },
+            facing: Orientation::South,
+        });
+
+        assert_eq!(actual, expected)
+    }
+
     #[test]
     fn reads_upper_right() {
         let mut input = Cursor::new("  31 24\n");
 
-        let actual = MissionPlan::read(&mut input).unwrap().upper_right;
-        let expected = Point { x: 31, y: 24 };
+        let actual = MissionPlan::read(&mut input).unwrap().world;
+        let expected = World::rectangle(Point { x: 31, y: 24 });
 
         assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn reads_both_corners() {
+        let mut input = Cursor::new("-2 -1 5 3\n-2 0 E\nF\n");
+
+        let mut plan = MissionPlan::read(&mut input).unwrap();
+        let expected = World::between(Point { x: -2, y: -1 }, Point { x: 5, y: 3 });
+
+        assert_eq!(plan.world, expected);
+        assert!(matches!(plan.next(), Some(Ok((robot, _))) if robot.position.x == -2));
+    }
+
+    #[test]
+    fn recognises_negative_points() {
+        let input = b"-3 -14";
+        let (actual, _) = point().parse(position::Stream::new(&input[..])).unwrap();
+        let expected = Point { x: -3, y: -14 };
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn does_not_recognise_numbers_out_of_range() {
+        let input = b"99999999999999999999 1";
+        let actual = point().parse(position::Stream::new(&input[..]));
+
+        assert!(actual.is_err());
+        assert!(
+            MissionPlan::read(&mut Cursor::new("5 3\n1 1 E\n99999999999999999999F\n"))
+                .unwrap()
+                .next()
+                .unwrap()
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "wide")]
+    #[test]
+    fn reads_worlds_billions_of_cells_across() {
+        let mut input = Cursor::new("-8000000000 -8000000000 8000000000 8000000000\n");
+        let actual = MissionPlan::read(&mut input).unwrap().world;
+        let expected = World::between(
+            Point {
+                x: -8_000_000_000,
+                y: -8_000_000_000,
+            },
+            Point {
+                x: 8_000_000_000,
+                y: 8_000_000_000,
+            },
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reads_a_mask() {
+        let mut input = Cursor::new("MASK 3 2\n#..\n###\n0 1 E\nF\n");
+
+        let mut plan = MissionPlan::read(&mut input).unwrap();
+        let expected = World {
+            grid: Grid::Square,
+            shape: Shape::Mask(Mask::from_art(&["#..", "###"])),
+        };
+
+        assert_eq!(plan.world, expected);
+        assert!(matches!(plan.next(), Some(Ok(_))));
+    }
+
+    #[test]
+    fn reads_mask_regions() {
+        let mut input = Cursor::new("REGIONS 2\n0 0 2 0\n-1 1 0 1\n");
+
+        let actual = MissionPlan::read(&mut input).unwrap().world;
+        let mut mask = Mask::new();
+        mask.insert_region(Point { x: 0, y: 0 }, Point { x: 2, y: 0 });
+        mask.insert_region(Point { x: -1, y: 1 }, Point { x: 0, y: 1 });
+        let expected = World {
+            grid: Grid::Square,
+            shape: Shape::Mask(mask),
+        };
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn does_not_recognise_masks_too_large_to_keep() {
+        let plans = [
+            "REGIONS 1\n0 0 200000 200000\n",
+            "REGIONS 2\n0 0 999 599\n0 1000 999 1599\n",
+            "REGIONS 4000000000\n0 0 1 1\n",
+            "MASK 200000 200000\n#\n",
+        ];
+
+        for plan in plans.iter() {
+            let mut input = Cursor::new(*plan);
+            assert!(MissionPlan::read(&mut input).is_err(), "{}", plan);
+        }
+    }
+
+    #[test]
+    fn reads_outcomes_labelled_by_mission() {
+        let mut input = Cursor::new("=== Mission 1\n1 1 E\n=== Day 2\n3 3 N LOST\n");
+        let outcomes = MissionOutcomes::read(&mut input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(matches!(outcomes[1], Outcome::Lost(_)));
+    }
+
+    #[test]
+    fn does_not_recognise_a_short_mask_row() {
+        let mut input = Cursor::new("MASK 3 2\n#.\n###\n");
+
+        assert!(MissionPlan::read(&mut input).is_err());
+    }
+
+    #[test]
+    fn reads_a_hex_grid() {
+        let mut input = Cursor::new("5 3 HEX\n1 1 E\nF\n");
+
+        let actual = MissionPlan::read(&mut input).unwrap().world;
+        let expected = World {
+            grid: Grid::Hex,
+            shape: Shape::Rectangle(World::ORIGIN, Point { x: 5, y: 3 }),
+        };
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn reads_a_hexagonal_grid() {
+        let mut input = Cursor::new("HEX 3\n1 1 E\nF\n");
+
+        let actual = MissionPlan::read(&mut input).unwrap().world;
+        let expected = World {
+            grid: Grid::Hex,
+            shape: Shape::Hexagon(3),
+        };
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn rejects_square_headings_on_a_hex_grid() {
+        let mut input = Cursor::new("5 3 HEX\n1 1 N\nF\n");
+
+        let actual = MissionPlan::read(&mut input).unwrap().next();
+
+        assert!(matches!(actual, Some(Err(_))));
+    }
+
+    #[test]
+    fn reads_a_volume() {
+        let mut input = Cursor::new("5 3 2\n1 1 0 E\nF\n");
+
+        let actual = FlightPlan::read(&mut input).unwrap().volume;
+        let expected = Volume {
+            upper: Point3 { x: 5, y: 3, z: 2 },
+        };
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn tells_grids_and_volumes_apart() {
+        let mut input = Cursor::new("5 3\n1 1 E\nF\n");
+        assert!(matches!(Plan::read(&mut input), Ok(Plan::Surface(_))));
+
+        let mut input = Cursor::new("5 3 2\n1 1 0 E\nF\n");
+        assert!(matches!(Plan::read(&mut input), Ok(Plan::Flight(_))));
+        assert!(MissionPlan::read(&mut Cursor::new("5 3 2\n")).is_err());
+    }
+
+    #[test]
+    fn recognises_a_drone() {
+        let input = b"4 5 1 NW+\n+FF-F\n";
+        let (actual, _) = drone().parse(position::Stream::new(&input[..])).unwrap();
+        let expected = (
+            Drone {
+                position: Point3 { x: 4, y: 5, z: 1 },
+                facing: Orientation::NorthWest,
+                pitch: Pitch::Up,
+            },
+            vec![Command::PitchUp, F, F, Command::PitchDown, F],
+        );
+
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn rejects_pitching_on_a_grid() {
+        let mut input = Cursor::new("5 3\n1 1 N\n+F\n");
+
+        let actual = MissionPlan::read(&mut input).unwrap().next();
+
+        assert!(matches!(actual, Some(Err(_))));
+    }
+
     #[test]
     fn reads_one_robot() {
         let mut input = Cursor::new("  31 24\n   1 1 E\nLFLFLFLF\n");
@@ -374,6 +1244,73 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn resumes_from_an_offset() {
+        let text = "5 3\n1 1 E\nRFRFRFRF\n\n3 2 N\nFRRFLLFFRRFLL\n\n0 3 W\nLLFFFLFLFL\n";
+
+        let mut input = Cursor::new(text);
+        let mut plan = MissionPlan::read(&mut input).unwrap();
+        let world = plan.world.clone();
+        let first = plan.next();
+        let offset = plan.offset();
+        let rest = plan.collect::<Vec<_>>();
+
+        let mut input = Cursor::new(text);
+        let resumed = MissionPlan::resume(&mut input, world, offset).unwrap();
+
+        assert!(matches!(first, Some(Ok(_))));
+        assert_eq!(resumed.collect::<Vec<_>>(), rest);
+    }
+
+    #[test]
+    fn reads_several_missions() {
+        let mut input = Cursor::new("5 3\n1 1 E\nF\n\n=== Day 2\n3 3\n0 0 N\nF\n===\n1 1 1\n");
+
+        let plan = match Plan::read(&mut input) {
+            Ok(Plan::Surface(plan)) => plan,
+            _ => panic!("Expected a mission on a grid"),
+        };
+        assert_eq!(plan.name, None);
+
+        let plan = match plan.next_plan() {
+            Some(Ok(Plan::Surface(mut plan))) => {
+                assert_eq!(plan.name.as_deref(), Some("Day 2"));
+                assert_eq!(plan.world, World::rectangle(Point { x: 3, y: 3 }));
+                assert!(matches!(plan.next(), Some(Ok(_))));
+                assert_eq!(plan.next(), None);
+                plan
+            }
+            _ => panic!("Expected a second mission"),
+        };
+
+        match plan.next_plan() {
+            Some(Ok(Plan::Flight(mut plan))) => {
+                assert_eq!(plan.name.as_deref(), Some("Mission 3"));
+                assert!(plan.next().is_none());
+                assert!(plan.next_plan().is_none());
+            }
+            _ => panic!("Expected a third mission"),
+        }
+    }
+
+    #[test]
+    fn skips_to_the_next_mission_after_an_error() {
+        let mut input = Cursor::new("=== A\n5 3\n1 1 Q\nF\n2 2 N\nF\n=== B\n3 3\n");
+
+        let mut plan = match Plan::read(&mut input) {
+            Ok(Plan::Surface(plan)) => plan,
+            _ => panic!("Expected a mission on a grid"),
+        };
+        assert_eq!(plan.name.as_deref(), Some("A"));
+        assert!(matches!(plan.next(), Some(Err(_))));
+        assert_eq!(plan.next(), None);
+
+        match plan.next_plan() {
+            Some(Ok(Plan::Surface(plan))) => assert_eq!(plan.name.as_deref(), Some("B")),
+            _ => panic!("Expected a second mission"),
+        }
+    }
+
     #[test]
     fn reads_one_outcome() {
         let mut input = Cursor::new("  22 11 E LOST\n");
@@ -1,52 +1,328 @@
-use colored::Colorize;
+use colored::{Color, Colorize};
+use difference::{Changeset, Difference};
 use itertools::Itertools;
 use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
 
+use crate::drone::Drone;
+use crate::geo::grid::Grid;
 use crate::geo::location::Point;
+use crate::geo::mask::Mask;
 use crate::geo::orientation::Orientation;
-use crate::mission::Outcome;
-use crate::robot::{Command, Robot};
+use crate::geo::space::{Course, Pitch, Point3, Volume};
+use crate::geo::world::{Shape, World};
+use crate::golden::Verdict;
+use crate::mission::{Heatmap, Outcome, Stats, Step, Tick};
+use crate::robot::{Command, Robot, Rover};
+use crate::scents::Scent;
 
-pub fn plan<I>(upper_right: Point, stream: I)
+// All output is written to a given writer, so it can go to STDOUT or to a file
+
+pub fn plan<W, I>(out: &mut W, world: &World, stream: I) -> io::Result<()>
 where
+    W: Write + ?Sized,
     I: Iterator<Item = (Robot, Vec<Command>)>,
 {
-    println!("{}", upper_right);
+    writeln!(out, "{}", world)?;
     for (robot, commands) in stream {
-        println!("{}\n{}\n", robot, commands.iter().format(""));
+        writeln!(out, "{}\n{}\n", robot, commands.iter().format(""))?;
     }
+    Ok(())
 }
 
-pub fn checks<I>(stream: I)
+// Prints each check, returning how many outcomes were correct out of how many checked
+pub fn checks<W, I>(out: &mut W, stream: I) -> io::Result<(usize, usize)>
 where
+    W: Write + ?Sized,
     I: Iterator<Item = (Outcome, Result<Outcome, String>)>,
 {
+    let (mut correct, mut total) = (0, 0);
     for (expected, actual) in stream {
-        if let Err(msg) = actual {
-            return eprintln!("{}", msg);
+        match actual {
+            Ok(actual) => {
+                correct += check(out, expected, actual)? as usize;
+                total += 1;
+            }
+            Err(msg) => {
+                eprintln!("{}", msg);
+                break;
+            }
         }
+    }
+    Ok((correct, total))
+}
+
+// Prints whether an outcome was as expected, returning whether it was
+pub fn check<W: Write + ?Sized>(
+    out: &mut W,
+    expected: Outcome,
+    actual: Outcome,
+) -> io::Result<bool> {
+    if actual == expected {
+        writeln!(out, "{}", format!("✓ {}", actual).green())?;
+    } else {
+        let err = format!("⨯ Expected: {}, got: {}", expected, actual).red();
+        writeln!(out, "{}", err)?;
+    }
+    Ok(actual == expected)
+}
 
-        let actual = actual.unwrap();
+// Prints an outcome which was expected but never given
+pub fn missing<W: Write + ?Sized>(out: &mut W, expected: Outcome) -> io::Result<()> {
+    let err = format!("⨯ Expected: {}, got nothing", expected).red();
+    writeln!(out, "{}", err)
+}
 
-        if actual == expected {
-            println!("{}", format!("✓ {}", actual).green());
-        } else {
-            let err = format!("⨯ Expected: {}, got: {}", expected, actual).red();
-            println!("{}", err);
+// Prints an outcome which was given but never expected
+pub fn unexpected<W: Write + ?Sized>(out: &mut W, actual: Outcome) -> io::Result<()> {
+    writeln!(out, "{}", format!("⨯ Unexpected: {}", actual).red())
+}
+
+pub fn summary<W: Write + ?Sized>(out: &mut W, (correct, total): (usize, usize)) -> io::Result<()> {
+    writeln!(out, "{} of {} outcomes correct", correct, total)
+}
+
+// Prints a table of which cases passed, followed by what went wrong with the others
+pub fn verdicts<W: Write + ?Sized>(out: &mut W, verdicts: &[Verdict]) -> io::Result<()> {
+    let width = verdicts.iter().map(|v| v.case.len()).max().unwrap_or(0);
+
+    for verdict in verdicts {
+        let (matching, lines) = verdict.matching();
+        let status = match (verdict.passed(), verdict.updated) {
+            (true, _) => "✓ pass".green(),
+            (false, true) => "↻ updated".yellow(),
+            (false, false) => "⨯ fail".red(),
+        };
+        writeln!(
+            out,
+            "{:<9}  {:<width$}  {}/{}",
+            status,
+            verdict.case,
+            matching,
+            lines,
+            width = width
+        )?;
+    }
+
+    for verdict in verdicts.iter().filter(|v| !v.passed()) {
+        writeln!(out, "\n{}", format!("=== {}", verdict.case).bold())?;
+        match (&verdict.expected, &verdict.actual) {
+            (_, Err(msg)) => writeln!(out, "{}", msg.red())?,
+            (None, Ok(_)) => writeln!(out, "{}", "No expected outcomes".red())?,
+            (Some(expected), Ok(actual)) => diff(out, expected, actual)?,
         }
     }
+
+    let passed = verdicts.iter().filter(|v| v.passed()).count();
+    writeln!(out, "\n{} of {} cases passed", passed, verdicts.len())
+}
+
+// Prints the lines expected but missing with a '-', and the lines not expected with a '+'
+pub fn diff<W: Write + ?Sized>(out: &mut W, expected: &str, actual: &str) -> io::Result<()> {
+    for change in Changeset::new(expected.trim_end(), actual.trim_end(), "\n").diffs {
+        let (text, prefix) = match &change {
+            Difference::Same(text) => (text, " "),
+            Difference::Rem(text) => (text, "-"),
+            Difference::Add(text) => (text, "+"),
+        };
+
+        for line in text.lines() {
+            let line = format!("{} {}", prefix, line);
+            match change {
+                Difference::Same(_) => writeln!(out, "{}", line)?,
+                Difference::Rem(_) => writeln!(out, "{}", line.red())?,
+                Difference::Add(_) => writeln!(out, "{}", line.green())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn stats<W, R>(out: &mut W, stats: &Stats<R>) -> io::Result<()>
+where
+    W: Write + ?Sized,
+    R: Rover,
+    R::Edge: fmt::Display + Ord,
+{
+    let losses = stats
+        .losses
+        .iter()
+        .sorted()
+        .format_with(", ", |(edge, n), f| f(&format_args!("{} {}", edge, n)));
+
+    writeln!(out, "Robots         {}", stats.robots)?;
+    writeln!(
+        out,
+        "Lost           {} ({:.1}%)",
+        stats.lost,
+        stats.loss_rate() * 100.0
+    )?;
+    writeln!(out, "Collided       {}", stats.collided)?;
+    writeln!(out, "Scents laid    {}", stats.scents)?;
+    writeln!(out, "Scented moves  {}", stats.scented)?;
+    writeln!(
+        out,
+        "Commands       {} executed, {} ignored",
+        stats.executed, stats.ignored
+    )?;
+    writeln!(
+        out,
+        "Path length    {:.1} on average, {} at most",
+        stats.average_path(),
+        stats.longest_path
+    )?;
+    writeln!(out, "Losses         {}", losses)
 }
 
-pub fn outcomes<I>(stream: I)
+// Heatmaps of worlds with more cells than this aren't drawn or listed, as that could take
+// forever and fill any disk
+const MAX_HEATMAP_CELLS: i128 = 1 << 16;
+
+// How many cells lie within a world's bounds, holes and all
+fn area(lower_left: Point, upper_right: Point) -> i128 {
+    let columns = (upper_right.x as i128 - lower_left.x as i128 + 1).max(0);
+    columns * (upper_right.y as i128 - lower_left.y as i128 + 1).max(0)
+}
+
+// Draws a character for each cell of the world, from '.' for cells no robot visited to
+// '@' for the most visited, coloured by how hot they are when colours are on
+pub fn heatmap<W>(out: &mut W, world: &World, heatmap: &Heatmap<Point>) -> io::Result<()>
 where
-    I: Iterator<Item = Result<Outcome, String>>,
+    W: Write + ?Sized,
+{
+    const RAMP: &[char] = &['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+    const COLOURS: &[Color] = &[
+        Color::White,
+        Color::Blue,
+        Color::Cyan,
+        Color::Green,
+        Color::Green,
+        Color::Yellow,
+        Color::Yellow,
+        Color::Red,
+        Color::BrightRed,
+    ];
+
+    let (lower_left, upper_right) = match world.bounds() {
+        Some(bounds) => bounds,
+        None => return Ok(()),
+    };
+    if area(lower_left, upper_right) > MAX_HEATMAP_CELLS {
+        return writeln!(
+            out,
+            "The world is too large to draw, with {} cells, {} of them visited",
+            area(lower_left, upper_right),
+            heatmap.len()
+        );
+    }
+    let hottest = heatmap.hottest().max(1);
+    let mut cells = 0;
+
+    for row in (lower_left.y..=upper_right.y).rev() {
+        let mut line = String::new();
+        for column in lower_left.x..=upper_right.x {
+            let position = world.position(column, row);
+            if !world.contains(position) {
+                line.push(' ');
+                continue;
+            }
+
+            cells += 1;
+            let visits = heatmap.visits(position);
+            let level = (visits * (RAMP.len() - 1)).div_ceil(hottest);
+            let cell = RAMP[level].to_string().color(COLOURS[level]);
+            line.push_str(&cell.to_string());
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    writeln!(
+        out,
+        "'{}' 0 to '{}' {} visits, {} of {} cells visited",
+        RAMP[0],
+        RAMP[RAMP.len() - 1],
+        heatmap.hottest(),
+        heatmap.len(),
+        cells
+    )
+}
+
+// Lists the visits to each cell of the world, including cells no robot visited
+pub fn heatmap_csv<W>(out: &mut W, world: &World, heatmap: &Heatmap<Point>) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    if let Some((lower_left, upper_right)) = world.bounds() {
+        if area(lower_left, upper_right) > MAX_HEATMAP_CELLS {
+            let cells = area(lower_left, upper_right);
+            let msg = format!("the world is too large to list, with {} cells", cells);
+            return Err(io::Error::other(msg));
+        }
+
+        writeln!(out, "x,y,visits")?;
+        for row in lower_left.y..=upper_right.y {
+            for column in lower_left.x..=upper_right.x {
+                let position = world.position(column, row);
+                if world.contains(position) {
+                    let visits = heatmap.visits(position);
+                    writeln!(out, "{},{},{}", position.x, position.y, visits)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Prints outcomes until one can't be had, writing why to errors, e.g. STDERR
+pub fn outcomes<W, E, I, R>(out: &mut W, errors: &mut E, stream: I) -> io::Result<()>
+where
+    W: Write + ?Sized,
+    E: Write + ?Sized,
+    I: Iterator<Item = Result<Outcome<R>, String>>,
+    R: fmt::Display,
 {
     for item in stream {
         match item {
-            Ok(outcome) => println!("{}", outcome),
-            Err(msg) => return eprintln!("{}", msg),
+            Ok(outcome) => self::outcome(out, &outcome)?,
+            Err(msg) => {
+                writeln!(errors, "{}", msg)?;
+                break;
+            }
         }
     }
+    Ok(())
+}
+
+// Marks the start of a named mission's output, in the same form as in the plan
+pub fn label<W: Write + ?Sized>(out: &mut W, name: Option<&str>) -> io::Result<()> {
+    match name {
+        Some(name) => writeln!(out, "=== {}", name),
+        None => Ok(()),
+    }
+}
+
+// Marks the start of the output for one of several input files
+pub fn file<W: Write + ?Sized>(out: &mut W, path: &Path) -> io::Result<()> {
+    writeln!(out, "==> {} <==", path.display())
+}
+
+pub fn outcome<W: Write + ?Sized, R: fmt::Display>(
+    out: &mut W,
+    outcome: &Outcome<R>,
+) -> io::Result<()> {
+    writeln!(out, "{}", outcome)
+}
+
+pub fn ticks<W: Write + ?Sized, R: fmt::Display>(out: &mut W, ticks: &[Tick<R>]) -> io::Result<()> {
+    for (t, tick) in ticks.iter().enumerate() {
+        let steps = tick
+            .steps
+            .iter()
+            .format_with(" | ", |(i, step), f| f(&format_args!("{}: {}", i, step)));
+        writeln!(out, "{} | {}", t + 1, steps)?;
+    }
+    Ok(())
 }
 
 // Display support
@@ -54,10 +330,14 @@ where
 impl std::fmt::Display for Orientation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let text = match self {
-            Orientation::North => 'N',
-            Orientation::East => 'E',
-            Orientation::South => 'S',
-            Orientation::West => 'W',
+            Orientation::North => "N",
+            Orientation::East => "E",
+            Orientation::South => "S",
+            Orientation::West => "W",
+            Orientation::NorthEast => "NE",
+            Orientation::SouthEast => "SE",
+            Orientation::SouthWest => "SW",
+            Orientation::NorthWest => "NW",
         };
         write!(f, "{}", text)
     }
@@ -75,22 +355,230 @@ impl std::fmt::Display for Point {
     }
 }
 
+impl std::fmt::Display for Drone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pitch = match self.pitch {
+            Pitch::Level => "",
+            Pitch::Up => "+",
+            Pitch::Down => "-",
+        };
+        write!(f, "{} {}{}", self.position, self.facing, pitch)
+    }
+}
+
+impl std::fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Course::Level(facing) => write!(f, "{}", facing),
+            Course::Up => write!(f, "+"),
+            Course::Down => write!(f, "-"),
+        }
+    }
+}
+
+impl std::fmt::Display for Scent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.position, self.heading)
+    }
+}
+
+impl std::fmt::Display for Point3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.z)
+    }
+}
+
+impl std::fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.upper)
+    }
+}
+
+impl std::fmt::Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.grid, &self.shape) {
+            (grid, Shape::Rectangle(lower_left, upper_right)) => {
+                if *lower_left != World::ORIGIN {
+                    write!(f, "{} ", lower_left)?;
+                }
+                write!(f, "{}", upper_right)?;
+                if grid == Grid::Hex {
+                    write!(f, " HEX")?;
+                }
+                Ok(())
+            }
+            (_, Shape::Hexagon(radius)) => write!(f, "HEX {}", radius),
+            (_, Shape::Mask(mask)) => write!(f, "{}", mask),
+        }
+    }
+}
+
+// Masks are written as ASCII art if they fit above and to the right of the origin,
+// and as a list of regions, one for each run of cells in a row, otherwise
+impl std::fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lower_left, upper_right) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return write!(f, "MASK 0 0"),
+        };
+
+        if lower_left.x >= 0 && lower_left.y >= 0 {
+            write!(f, "MASK {} {}", upper_right.x + 1, upper_right.y + 1)?;
+            for y in (0..=upper_right.y).rev() {
+                let row = (0..=upper_right.x).map(|x| match self.contains(Point { x, y }) {
+                    true => '#',
+                    false => '.',
+                });
+                write!(f, "\n{}", row.collect::<String>())?;
+            }
+            return Ok(());
+        }
+
+        let runs = self
+            .cells()
+            .into_iter()
+            .map(|cell| (cell, cell))
+            .coalesce(|(start, end), (next, _)| {
+                if next.y == end.y && next.x == end.x + 1 {
+                    Ok((start, next))
+                } else {
+                    Err(((start, end), (next, next)))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "REGIONS {}", runs.len())?;
+        for (start, end) in runs {
+            write!(f, "\n{} {}", start, end)?;
+        }
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let text = match self {
             Command::Left => 'L',
             Command::Right => 'R',
             Command::Forward => 'F',
+            Command::Backward => 'B',
+            Command::UTurn => 'U',
+            Command::HalfLeft => 'l',
+            Command::HalfRight => 'r',
+            Command::PitchUp => '+',
+            Command::PitchDown => '-',
+            Command::Custom(letter) => *letter,
         };
         write!(f, "{}", text)
     }
 }
 
-impl std::fmt::Display for Outcome {
+impl<R: fmt::Display> std::fmt::Display for Outcome<R> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Outcome::Success(robot) => write!(f, "{}", robot),
             Outcome::Lost(robot) => write!(f, "{} LOST", robot),
+            Outcome::Collided(robot) => write!(f, "{} COLLIDED", robot),
+        }
+    }
+}
+
+impl<R: fmt::Display> std::fmt::Display for Step<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Moved(robot) => write!(f, "{}", robot),
+            Step::Scented(robot) => write!(f, "{} SCENTED", robot),
+            Step::Blocked(robot) => write!(f, "{} BLOCKED", robot),
+            Step::Collided(robot) => write!(f, "{} COLLIDED", robot),
+            Step::Lost(robot) => write!(f, "{} LOST", robot),
+        }
+    }
+}
+
+// Text without the escape codes colouring it, so tests don't depend on whether colours
+// are on, which is decided for the whole process
+#[cfg(test)]
+pub fn uncoloured(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
         }
     }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tallies_checks() {
+        let robot = Robot {
+            position: Point { x: 1, y: 1 },
+            facing: Orientation::East,
+        };
+        let results = vec![
+            (Outcome::Success(robot), Ok(Outcome::Success(robot))),
+            (Outcome::Success(robot), Ok(Outcome::Lost(robot))),
+        ];
+
+        let mut out = Vec::new();
+        let tally = checks(&mut out, results.into_iter()).unwrap();
+
+        assert_eq!(tally, (1, 2));
+        assert_eq!(
+            uncoloured(&String::from_utf8(out).unwrap()),
+            "✓ 1 1 E\n⨯ Expected: 1 1 E, got: 1 1 E LOST\n"
+        );
+        assert_eq!(uncoloured(&"⨯ fail".red().to_string()), "⨯ fail");
+    }
+
+    #[test]
+    fn draws_heatmaps() {
+        let world = World::rectangle(Point { x: 2, y: 1 });
+        let mut visits = Heatmap::new();
+        for &(x, y) in &[(0, 0), (0, 0), (1, 0), (1, 1), (0, 0), (0, 1)] {
+            visits.visit(Point { x, y });
+        }
+
+        let mut drawn = Vec::new();
+        heatmap(&mut drawn, &world, &visits).unwrap();
+        let mut csv = Vec::new();
+        heatmap_csv(&mut csv, &world, &visits).unwrap();
+
+        assert_eq!(
+            uncoloured(&String::from_utf8(drawn).unwrap()),
+            "==.\n@=.\n'.' 0 to '@' 3 visits, 4 of 6 cells visited\n"
+        );
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "x,y,visits\n0,0,3\n1,0,1\n2,0,0\n0,1,1\n1,1,1\n2,1,0\n"
+        );
+    }
+
+    #[test]
+    fn does_not_draw_heatmaps_of_huge_worlds() {
+        let world = World::rectangle(Point { x: 999, y: 999 });
+        let mut visits = Heatmap::new();
+        visits.visit(Point { x: 0, y: 0 });
+
+        let mut drawn = Vec::new();
+        heatmap(&mut drawn, &world, &visits).unwrap();
+        let mut csv = Vec::new();
+        let listed = heatmap_csv(&mut csv, &world, &visits);
+
+        assert_eq!(
+            String::from_utf8(drawn).unwrap(),
+            "The world is too large to draw, with 1000000 cells, 1 of them visited\n"
+        );
+        assert!(listed
+            .unwrap_err()
+            .to_string()
+            .contains("too large to list"));
+        assert!(csv.is_empty());
+    }
 }
@@ -0,0 +1,438 @@
+use std::io::Cursor;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::commands::CommandSet;
+use crate::generator::{Generator, Settings};
+use crate::geo::grid::Grid;
+use crate::geo::location::{Coord, Point};
+use crate::geo::orientation::Compass;
+use crate::mission::{Collisions, Mission, Obstacles, Outcome, Step};
+use crate::parser::{MissionOutcomes, MissionPlan, Plan};
+use crate::robot::{Command, Robot};
+
+// The Python extension module, built with maturin:
+//
+//     maturin develop
+//
+// Plans, outcomes and worlds are read and written as text, just like the command line
+// reads and prints them, so results can be compared with its output directly.
+
+type Fleet = Vec<(Robot, Vec<Command>)>;
+
+fn invalid(msg: String) -> PyErr {
+    PyValueError::new_err(msg)
+}
+
+fn obstacles(obstacles: bool, lost_obstacles: bool, collisions: bool) -> Option<Obstacles> {
+    if !(obstacles || lost_obstacles) {
+        return None;
+    }
+
+    let collisions = if collisions {
+        Collisions::Report
+    } else {
+        Collisions::Ignore
+    };
+
+    Some(Obstacles {
+        lost: lost_obstacles,
+        collisions,
+    })
+}
+
+// A robot and the commands it's given
+#[pyclass(name = "Robot", module = "martian_robots")]
+#[derive(Clone)]
+struct PyRobot {
+    robot: Robot,
+    commands: Vec<Command>,
+}
+
+#[pymethods]
+impl PyRobot {
+    #[new]
+    #[pyo3(signature = (x, y, facing, commands = ""))]
+    fn new(x: Coord, y: Coord, facing: &str, commands: &str) -> PyResult<PyRobot> {
+        let known = CommandSet::new();
+        let commands = commands
+            .chars()
+            .map(|letter| {
+                known
+                    .command(letter)
+                    .ok_or_else(|| invalid(format!("Unknown command '{}'", letter)))
+            })
+            .collect::<PyResult<_>>()?;
+
+        Ok(PyRobot {
+            robot: Robot {
+                position: Point { x, y },
+                facing: facing.parse().map_err(invalid)?,
+            },
+            commands,
+        })
+    }
+
+    #[getter]
+    fn x(&self) -> Coord {
+        self.robot.position.x
+    }
+
+    #[getter]
+    fn y(&self) -> Coord {
+        self.robot.position.y
+    }
+
+    #[getter]
+    fn facing(&self) -> String {
+        self.robot.facing.to_string()
+    }
+
+    #[getter]
+    fn commands(&self) -> String {
+        self.commands.iter().map(ToString::to_string).collect()
+    }
+
+    fn __str__(&self) -> String {
+        format!("{}\n{}", self.robot, self.commands())
+    }
+
+    fn __repr__(&self) -> String {
+        let (x, y, facing) = (self.x(), self.y(), self.facing());
+        format!("Robot({}, {}, '{}', '{}')", x, y, facing, self.commands())
+    }
+}
+
+// Where a robot ended up, written as the command line prints it
+#[pyclass(name = "Outcome", module = "martian_robots", eq)]
+#[derive(Clone, PartialEq)]
+struct PyOutcome {
+    outcome: Outcome,
+}
+
+#[pymethods]
+impl PyOutcome {
+    #[getter]
+    fn x(&self) -> Coord {
+        self.robot().position.x
+    }
+
+    #[getter]
+    fn y(&self) -> Coord {
+        self.robot().position.y
+    }
+
+    #[getter]
+    fn facing(&self) -> String {
+        self.robot().facing.to_string()
+    }
+
+    #[getter]
+    fn lost(&self) -> bool {
+        matches!(self.outcome, Outcome::Lost(_))
+    }
+
+    #[getter]
+    fn collided(&self) -> bool {
+        matches!(self.outcome, Outcome::Collided(_))
+    }
+
+    fn __str__(&self) -> String {
+        self.outcome.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Outcome('{}')", self.outcome)
+    }
+}
+
+impl PyOutcome {
+    fn robot(&self) -> Robot {
+        match self.outcome {
+            Outcome::Success(robot) | Outcome::Lost(robot) | Outcome::Collided(robot) => robot,
+        }
+    }
+}
+
+// One command carried out by a robot, and where it left the robot
+#[pyclass(name = "Step", module = "martian_robots", get_all)]
+#[derive(Clone)]
+struct PyStep {
+    x: Coord,
+    y: Coord,
+    facing: String,
+    kind: &'static str, // moved, scented, blocked, collided or lost
+}
+
+#[pymethods]
+impl PyStep {
+    fn __repr__(&self) -> String {
+        let (x, y, facing, kind) = (self.x, self.y, &self.facing, self.kind);
+        format!("Step({}, {}, '{}', '{}')", x, y, facing, kind)
+    }
+}
+
+impl From<Step> for PyStep {
+    fn from(step: Step) -> PyStep {
+        let kind = match step {
+            Step::Moved(_) => "moved",
+            Step::Scented(_) => "scented",
+            Step::Blocked(_) => "blocked",
+            Step::Collided(_) => "collided",
+            Step::Lost(_) => "lost",
+        };
+        let robot = step.robot();
+
+        PyStep {
+            x: robot.position.x,
+            y: robot.position.y,
+            facing: robot.facing.to_string(),
+            kind,
+        }
+    }
+}
+
+// Every step a robot took on its way to its outcome
+#[pyclass(name = "Trace", module = "martian_robots", get_all)]
+#[derive(Clone)]
+struct PyTrace {
+    robot: PyRobot,
+    steps: Vec<PyStep>,
+    outcome: PyOutcome,
+}
+
+// A mission read from a plan, with the world written as the plan's first line
+#[pyclass(name = "Plan", module = "martian_robots", get_all)]
+#[derive(Clone)]
+struct PyPlan {
+    name: Option<String>,
+    world: String,
+    robots: Vec<PyRobot>,
+}
+
+// A mission on a grid, which remembers scents and any parked robots between robots
+#[pyclass(name = "Mission", module = "martian_robots")]
+struct PyMission {
+    mission: Mission<Fleet, (Robot, Vec<Command>)>,
+}
+
+#[pymethods]
+impl PyMission {
+    #[new]
+    #[pyo3(signature = (world, obstacles = false, lost_obstacles = false, collisions = false))]
+    fn new(
+        world: &str,
+        obstacles: bool,
+        lost_obstacles: bool,
+        collisions: bool,
+    ) -> PyResult<PyMission> {
+        let mut input = Cursor::new(world);
+        let world = MissionPlan::read(&mut input).map_err(invalid)?.world;
+
+        let mut mission = Mission::in_world(world, Vec::new());
+        if let Some(obstacles) = self::obstacles(obstacles, lost_obstacles, collisions) {
+            mission = mission.with_obstacles(obstacles);
+        }
+
+        Ok(PyMission { mission })
+    }
+
+    fn dispatch(&mut self, robot: &PyRobot) -> PyOutcome {
+        let outcome = self.mission.dispatch(robot.robot, &robot.commands);
+        PyOutcome { outcome }
+    }
+
+    fn trace(&mut self, robot: &PyRobot) -> PyTrace {
+        let mut steps = Vec::new();
+        let outcome = self
+            .mission
+            .dispatch_with(robot.robot, &robot.commands, |step| steps.push(step.into()));
+
+        PyTrace {
+            robot: robot.clone(),
+            steps,
+            outcome: PyOutcome { outcome },
+        }
+    }
+
+    // The scents left so far, as (x, y, heading) tuples
+    #[getter]
+    fn scents(&self) -> Vec<(Coord, Coord, String)> {
+        let mut scents = self.mission.scents();
+        scents.sort();
+
+        scents
+            .into_iter()
+            .map(|(cell, heading)| (cell.x, cell.y, heading.to_string()))
+            .collect()
+    }
+}
+
+// Generates the same pseudo-random robots as the generate subcommand given the same seed
+// and settings
+#[pyclass(name = "Generator", module = "martian_robots")]
+struct PyGenerator {
+    settings: Settings,
+}
+
+#[pymethods]
+impl PyGenerator {
+    #[new]
+    #[pyo3(signature = (seed = 12345, eight_way = false, hex = false, lower_left = None, blob = false, hexagon = false))]
+    fn new(
+        seed: u64,
+        eight_way: bool,
+        hex: bool,
+        lower_left: Option<(Coord, Coord)>,
+        blob: bool,
+        hexagon: bool,
+    ) -> PyResult<PyGenerator> {
+        let settings = Settings {
+            compass: if eight_way {
+                Compass::Eight
+            } else {
+                Compass::Four
+            },
+            grid: if hex { Grid::Hex } else { Grid::Square },
+            lower_left: lower_left.map(|(x, y)| Point { x, y }),
+            blob,
+            hexagon,
+            ..Settings::new(seed)
+        };
+        settings.check().map_err(invalid)?;
+
+        Ok(PyGenerator { settings })
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.settings.seed
+    }
+
+    #[getter]
+    fn world(&self) -> String {
+        self.generator().world().to_string()
+    }
+
+    fn robots(&self, count: usize) -> Vec<PyRobot> {
+        self.generator()
+            .take(count)
+            .map(|(robot, commands)| PyRobot { robot, commands })
+            .collect()
+    }
+
+    // The plan for a number of robots, as the generate subcommand writes it
+    fn plan(&self, count: usize) -> String {
+        let generator = self.generator();
+        let mut plan = Vec::new();
+        crate::print::plan(&mut plan, &generator.world(), generator.take(count)).unwrap();
+
+        String::from_utf8(plan).unwrap()
+    }
+
+    // The outcomes a number of robots should have, as the verify subcommand expects them
+    #[pyo3(signature = (count, obstacles = false, lost_obstacles = false, collisions = false))]
+    fn expected(
+        &self,
+        count: usize,
+        obstacles: bool,
+        lost_obstacles: bool,
+        collisions: bool,
+    ) -> Vec<PyOutcome> {
+        let mission = self.generator().mission();
+        let mission = match self::obstacles(obstacles, lost_obstacles, collisions) {
+            Some(obstacles) => mission.with_obstacles(obstacles),
+            None => mission,
+        };
+
+        mission
+            .take(count)
+            .map(|outcome| PyOutcome { outcome })
+            .collect()
+    }
+}
+
+impl PyGenerator {
+    // The settings were checked when the generator was made
+    fn generator(&self) -> Generator {
+        self.settings.generator().unwrap()
+    }
+}
+
+// Reads every mission in a plan
+#[pyfunction]
+fn parse_plan(text: &str) -> PyResult<Vec<PyPlan>> {
+    let mut input = Cursor::new(text);
+    let mut next = Some(Plan::read(&mut input));
+    let mut plans = Vec::new();
+
+    while let Some(plan) = next.take() {
+        let mut plan = match plan.map_err(invalid)? {
+            Plan::Surface(plan) => plan,
+            Plan::Flight(_) => {
+                return Err(invalid(
+                    "Only plans for robots on a grid can be read".into(),
+                ))
+            }
+        };
+        let robots = (&mut plan)
+            .map(|robot| robot.map(|(robot, commands)| PyRobot { robot, commands }))
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
+
+        plans.push(PyPlan {
+            name: plan.name.clone(),
+            world: plan.world.to_string(),
+            robots,
+        });
+        next = plan.next_plan();
+    }
+
+    Ok(plans)
+}
+
+// Reads outcomes as the command line prints them
+#[pyfunction]
+fn parse_outcomes(text: &str) -> PyResult<Vec<PyOutcome>> {
+    let mut input = Cursor::new(text);
+
+    MissionOutcomes::read(&mut input)
+        .map(|outcome| outcome.map(|outcome| PyOutcome { outcome }))
+        .collect::<Result<_, _>>()
+        .map_err(invalid)
+}
+
+// Runs every mission in a plan, returning the outcomes of all their robots in order
+#[pyfunction]
+#[pyo3(signature = (text, obstacles = false, lost_obstacles = false, collisions = false))]
+fn run(
+    text: &str,
+    obstacles: bool,
+    lost_obstacles: bool,
+    collisions: bool,
+) -> PyResult<Vec<PyOutcome>> {
+    let mut outcomes = Vec::new();
+
+    for plan in parse_plan(text)? {
+        let mut mission = PyMission::new(&plan.world, obstacles, lost_obstacles, collisions)?;
+        outcomes.extend(plan.robots.iter().map(|robot| mission.dispatch(robot)));
+    }
+
+    Ok(outcomes)
+}
+
+#[pymodule]
+fn martian_robots(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRobot>()?;
+    m.add_class::<PyOutcome>()?;
+    m.add_class::<PyStep>()?;
+    m.add_class::<PyTrace>()?;
+    m.add_class::<PyPlan>()?;
+    m.add_class::<PyMission>()?;
+    m.add_class::<PyGenerator>()?;
+    m.add_function(wrap_pyfunction!(parse_plan, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_outcomes, m)?)?;
+    m.add_function(wrap_pyfunction!(run, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use std::io::{self, Write};
+
+use crate::commands::CommandSet;
+use crate::geo::location::Point;
+use crate::geo::world::World;
+use crate::mission::{Mission, MissionState, Step};
+use crate::parser::{self, MissionPlan};
+use crate::robot::{Command, Robot, Rover};
+use crate::scents::Scent;
+
+const HELP: &str = "\
+grid 5 3     start over on a new grid, given like the first line of a plan
+place 1 1 E  place a new robot, keeping the scents left so far
+RFRF         carry out commands, showing where each one leaves the robot
+scents       list the scents left so far
+undo         take back the last command or placement
+help         show this
+quit         stop
+";
+
+type Fleet = Vec<(Robot, Vec<Command>)>;
+
+// How things stood before a command or a placement, so it can be taken back
+struct Snapshot {
+    robot: Option<Robot>,
+    state: MissionState,
+}
+
+// A mission driven by hand, one line at a time, e.g. to explore how scents work
+pub struct Session {
+    mission: Mission<Fleet, (Robot, Vec<Command>)>,
+    robot: Option<Robot>, // none until one is placed, or once it's lost
+    history: Vec<Snapshot>,
+}
+
+impl Session {
+    pub fn new(world: World) -> Session {
+        Session {
+            mission: Mission::in_world(world, Vec::new()),
+            robot: None,
+            history: Vec::new(),
+        }
+    }
+
+    // Starts with the scents left by an earlier mission
+    pub fn with_scents(self, scents: &[Scent]) -> Session {
+        Session {
+            mission: self
+                .mission
+                .with_scents(scents.iter().copied().map(Scent::into)),
+            ..self
+        }
+    }
+
+    // The scents and so on left so far, e.g. to save them
+    pub fn state(&self) -> MissionState {
+        self.mission.state()
+    }
+
+    // Handles a line, returning whether to carry on
+    pub fn handle<W: Write + ?Sized>(&mut self, line: &str, out: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let result = match words.split_first() {
+            None => Ok(()),
+            Some((&"quit", _)) | Some((&"exit", _)) => return Ok(false),
+            Some((&"help", _)) => written(write!(out, "{}", HELP)),
+            Some((&"grid", header)) => self.grid(&header.join(" "), out),
+            Some((&"place", args)) => self.place(args, out),
+            Some((&"scents", _)) => self.scents(out),
+            Some((&"undo", _)) => self.undo(out),
+            Some(_) => self.run(&words.concat(), out),
+        };
+
+        if let Err(msg) = result {
+            writeln!(out, "{}", msg)?;
+        }
+        Ok(true)
+    }
+
+    fn grid<W: Write + ?Sized>(&mut self, header: &str, out: &mut W) -> Result<(), String> {
+        let mut input = io::Cursor::new(header);
+        let world = MissionPlan::read(&mut input)?.world;
+
+        *self = Session::new(world);
+        written(writeln!(out, "New grid {}", self.mission.world))
+    }
+
+    fn place<W: Write + ?Sized>(&mut self, args: &[&str], out: &mut W) -> Result<(), String> {
+        let robot = match args {
+            [x, y, facing] => Robot {
+                position: Point {
+                    x: x.parse().map_err(|_| format!("Invalid x '{}'", x))?,
+                    y: y.parse().map_err(|_| format!("Invalid y '{}'", y))?,
+                },
+                facing: facing.parse()?,
+            },
+            _ => return Err("Place a robot like this: place 1 1 E".to_string()),
+        };
+        if !robot.within(&self.mission.world) {
+            return Err(format!("{} is off the grid", robot.position));
+        }
+        parser::validate(&self.mission.world, robot, Vec::new())?;
+
+        self.save();
+        self.robot = Some(robot);
+        written(writeln!(out, "{}", robot))
+    }
+
+    fn run<W: Write + ?Sized>(&mut self, letters: &str, out: &mut W) -> Result<(), String> {
+        let known = CommandSet::new();
+        let commands = letters
+            .chars()
+            .map(|letter| {
+                known
+                    .command(letter)
+                    .ok_or_else(|| format!("Unknown command '{}', try help", letter))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let robot = match self.robot {
+            Some(robot) => robot,
+            None => return Err("Place a robot first, e.g. place 1 1 E".to_string()),
+        };
+        let (_, commands) = parser::validate(&self.mission.world, robot, commands)?;
+
+        for command in commands {
+            let robot = match self.robot {
+                Some(robot) => robot,
+                None => return Err("Place a robot first, e.g. place 1 1 E".to_string()),
+            };
+
+            self.save();
+            let mut step = None;
+            self.mission
+                .dispatch_with(robot, &[command], |s| step = Some(s));
+
+            let step = step.unwrap();
+            self.robot = match step {
+                Step::Lost(_) | Step::Collided(_) => None,
+                _ => Some(step.robot()),
+            };
+            written(writeln!(out, "{} {}", command, step))?;
+        }
+        Ok(())
+    }
+
+    fn scents<W: Write + ?Sized>(&self, out: &mut W) -> Result<(), String> {
+        let scents = self.mission.state().scents;
+        if scents.is_empty() {
+            return written(writeln!(out, "No scents yet"));
+        }
+
+        for scent in scents {
+            written(writeln!(out, "{}", scent))?;
+        }
+        Ok(())
+    }
+
+    fn undo<W: Write + ?Sized>(&mut self, out: &mut W) -> Result<(), String> {
+        let snapshot = self.history.pop().ok_or("Nothing to undo")?;
+        let world = self.mission.world.clone();
+
+        self.mission = Mission::in_world(world, Vec::new()).with_state(snapshot.state);
+        self.robot = snapshot.robot;
+
+        match self.robot {
+            Some(robot) => written(writeln!(out, "Back to {}", robot)),
+            None => written(writeln!(out, "Back to no robot")),
+        }
+    }
+
+    fn save(&mut self) {
+        self.history.push(Snapshot {
+            robot: self.robot,
+            state: self.mission.state(),
+        });
+    }
+}
+
+fn written(result: io::Result<()>) -> Result<(), String> {
+    result.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::grid::Grid;
+    use crate::geo::orientation::Orientation::North;
+
+    fn session(lines: &[&str]) -> String {
+        let mut session = Session::new(World::rectangle(Point { x: 5, y: 3 }));
+        let mut out = Vec::new();
+        for line in lines {
+            session.handle(line, &mut out).unwrap();
+        }
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn drives_robots_by_hand() {
+        let out = session(&[
+            "place 3 2 N",
+            "FRRFLLFF",
+            "place 0 3 W",
+            "LLFFFL",
+            "FLFL",
+            "scents",
+        ]);
+
+        assert_eq!(
+            out,
+            "3 2 N\nF 3 3 N\nR 3 3 E\nR 3 3 S\nF 3 2 S\nL 3 2 E\nL 3 2 N\nF 3 3 N\nF 3 3 N LOST\n\
+             0 3 W\nL 0 3 S\nL 0 3 E\nF 1 3 E\nF 2 3 E\nF 3 3 E\nL 3 3 N\n\
+             F 3 3 N SCENTED\nL 3 3 W\nF 2 3 W\nL 2 3 S\n3 3 N\n"
+        );
+    }
+
+    #[test]
+    fn refuses_what_a_plan_would_refuse() {
+        let mut session = Session::new(World {
+            grid: Grid::Hex,
+            ..World::rectangle(Point { x: 5, y: 3 })
+        });
+        let mut out = Vec::new();
+        for line in &["place 1 1 N", "place 1 1 NE", "l"] {
+            session.handle(line, &mut out).unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Heading N is not available on a hex grid\n1 1 NE\n\
+             Half turns are not available on a hex grid\n"
+        );
+    }
+
+    #[test]
+    fn starts_with_scents_left_before() {
+        let scents = [Scent {
+            position: Point { x: 3, y: 3 },
+            heading: North,
+        }];
+        let mut session = Session::new(World::rectangle(Point { x: 5, y: 3 })).with_scents(&scents);
+        let mut out = Vec::new();
+        session.handle("place 3 3 N", &mut out).unwrap();
+        session.handle("F", &mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "3 3 N\nF 3 3 N SCENTED\n");
+        assert_eq!(session.state().scents, scents);
+    }
+
+    #[test]
+    fn undoes_commands_and_scents() {
+        let out = session(&[
+            "place 3 3 N",
+            "F",
+            "scents",
+            "undo",
+            "scents",
+            "undo",
+            "F",
+            "undo",
+        ]);
+
+        assert_eq!(
+            out,
+            "3 3 N\nF 3 3 N LOST\n3 3 N\nBack to 3 3 N\nNo scents yet\nBack to no robot\n\
+             Place a robot first, e.g. place 1 1 E\nNothing to undo\n"
+        );
+    }
+}
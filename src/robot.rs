@@ -1,5 +1,12 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::commands::CommandSet;
+use crate::geo::grid::Grid;
 use crate::geo::location;
 use crate::geo::orientation;
+use crate::geo::orientation::TurnDirection;
+use crate::geo::world::World;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub struct Robot {
@@ -12,31 +19,157 @@ pub enum Command {
     Left,
     Right,
     Forward,
+    Backward,
+    UTurn,
+    HalfLeft,
+    HalfRight,
+    PitchUp,      // only understood by flying robots
+    PitchDown,    // only understood by flying robots
+    Custom(char), // registered in a CommandSet, which knows how to apply it
+}
+
+impl Command {
+    // The direction a robot facing a given way travels in when executing the command
+    pub fn heading(self, facing: orientation::Orientation) -> orientation::Orientation {
+        match self {
+            Command::Backward => facing.turn(orientation::TurnDirection::Around),
+            _ => facing,
+        }
+    }
 }
 
+// Robots moving beyond the range of coordinates are off any grid, so they go nowhere
 impl Robot {
-    pub fn advance(self: Robot, command: Command) -> Robot {
-        match command {
-            Command::Left => Robot {
-                position: self.position,
-                facing: self.facing.turn(orientation::TurnDirection::Left),
-            },
-            Command::Right => Robot {
-                position: self.position,
-                facing: self.facing.turn(orientation::TurnDirection::Right),
-            },
-            Command::Forward => Robot {
-                position: self.position + self.facing,
+    pub fn advance(self: Robot, command: Command) -> Option<Robot> {
+        self.advance_on(Grid::Square, command)
+    }
+
+    pub fn advance_on(self: Robot, grid: Grid, command: Command) -> Option<Robot> {
+        let turn = |direction| Robot {
+            position: self.position,
+            facing: grid.turn(self.facing, direction),
+        };
+
+        Some(match command {
+            Command::Left => turn(TurnDirection::Left),
+            Command::Right => turn(TurnDirection::Right),
+            Command::Forward | Command::Backward => Robot {
+                position: self
+                    .position
+                    .checked_add(grid.offset(command.heading(self.facing)))?,
                 facing: self.facing,
             },
-        }
+            Command::UTurn => turn(TurnDirection::Around),
+            Command::HalfLeft => turn(TurnDirection::HalfLeft),
+            Command::HalfRight => turn(TurnDirection::HalfRight),
+            Command::PitchUp | Command::PitchDown | Command::Custom(_) => self,
+        })
+    }
+}
+
+// Anything which can be sent on a mission: a robot occupying a cell of some world,
+// moving between cells as it executes commands
+pub trait Rover: Copy + PartialEq + Debug {
+    type Cell: Copy + Eq + Hash + Debug;
+    type Heading: Copy + Eq + Hash + Debug; // a direction of travel, which scents are left for
+    type Edge: Copy + Eq + Hash + Debug; // where rovers leave the world, counting losses
+    type World: Clone + Debug;
+
+    fn cell(&self) -> Self::Cell;
+
+    fn within(&self, world: &Self::World) -> bool;
+
+    // The state of the rover after executing a command, whether it stays in the world or not,
+    // or nothing if it would move beyond the range of coordinates, and so off any world
+    fn execute(self, command: Command, commands: &CommandSet, world: &Self::World) -> Option<Self>;
+
+    // The direction the rover travels in when executing a command
+    fn heading(&self, command: Command, commands: &CommandSet) -> Self::Heading;
+
+    // The edge of the world the rover leaves over, executing a command which loses it
+    fn edge(&self, command: Command, commands: &CommandSet, world: &Self::World) -> Self::Edge;
+
+    // How many cells a dense scent map of the world needs, for worlds which can have one
+    fn scent_cells(_world: &Self::World) -> Option<usize> {
+        None
+    }
+
+    // Where a scent is kept in a dense scent map: the index of its cell's byte, and the
+    // bit for its heading in it
+    fn scent_slot(
+        _world: &Self::World,
+        _cell: Self::Cell,
+        _heading: Self::Heading,
+    ) -> Option<(usize, u8)> {
+        None
+    }
+}
+
+impl Rover for Robot {
+    type Cell = location::Point;
+    type Heading = orientation::Orientation;
+    type World = World;
+    type Edge = orientation::Orientation; // N, E, S or W for a side, or NE and so on for a corner
+
+    fn cell(&self) -> location::Point {
+        self.position
+    }
+
+    fn within(&self, world: &World) -> bool {
+        world.contains(self.position)
+    }
+
+    fn execute(self, command: Command, commands: &CommandSet, world: &World) -> Option<Robot> {
+        commands.apply(self, command, world.grid)
+    }
+
+    fn heading(&self, command: Command, commands: &CommandSet) -> orientation::Orientation {
+        commands.heading(command, self.facing)
+    }
+
+    fn edge(
+        &self,
+        command: Command,
+        commands: &CommandSet,
+        world: &World,
+    ) -> orientation::Orientation {
+        let heading = self.heading(command, commands);
+
+        // where the robot would have gone, which may be beyond the range of coordinates
+        let (x, y) = match self.execute(command, commands, world) {
+            Some(robot) => (robot.position.x as i128, robot.position.y as i128),
+            None => {
+                let step = world.grid.offset(heading);
+                let x = self.position.x as i128 + step.x as i128;
+                (x, self.position.y as i128 + step.y as i128)
+            }
+        };
+
+        // leaving through a hole in a mask, or the side of a hexagon, crosses none of the
+        // world's bounds, so those losses are counted by heading
+        world.beyond(x, y).unwrap_or(heading)
+    }
+
+    fn scent_cells(world: &World) -> Option<usize> {
+        world.cell_count()
+    }
+
+    // there are eight orientations, so a byte has a bit for each
+    fn scent_slot(
+        world: &World,
+        cell: location::Point,
+        heading: orientation::Orientation,
+    ) -> Option<(usize, u8)> {
+        world
+            .cell_index(cell)
+            .map(|index| (index, 1 << heading as u8))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::geo::location::Point;
+    use crate::geo::location::{Coord, Point};
     use crate::geo::orientation::Orientation;
 
     #[test]
@@ -51,7 +184,7 @@ mod tests {
             facing: Orientation::North,
         };
         let actual = robot.advance(Command::Left);
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Some(expected));
     }
 
     #[test]
@@ -66,7 +199,7 @@ mod tests {
             facing: Orientation::South,
         };
         let actual = robot.advance(Command::Right);
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Some(expected));
     }
 
     #[test]
@@ -82,6 +215,127 @@ mod tests {
             facing: Orientation::East,
         };
         let actual = robot.advance(Command::Forward);
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_goes_nowhere_beyond_the_range_of_coordinates() {
+        let robot = Robot {
+            position: Point {
+                x: Coord::MAX,
+                y: Coord::MIN,
+            },
+            facing: Orientation::East,
+        };
+
+        assert_eq!(robot.advance(Command::Forward), None);
+        assert_eq!(
+            robot.advance(Command::UTurn).unwrap().facing,
+            Orientation::West
+        );
+        assert_eq!(
+            robot
+                .advance_on(Grid::Hex, Command::HalfRight)
+                .unwrap()
+                .position,
+            robot.position
+        );
+        assert_eq!(
+            robot
+                .advance(Command::HalfRight)
+                .unwrap()
+                .advance(Command::Forward),
+            None
+        );
+    }
+
+    #[test]
+    fn robot_moves_backward() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 0, y: 2 },
+            facing: Orientation::East,
+        };
+        let actual = robot.advance(Command::Backward);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_turns_around() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::West,
+        };
+        let actual = robot.advance(Command::UTurn);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_turns_half_left() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::NorthEast,
+        };
+        let actual = robot.advance(Command::HalfLeft);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_moves_diagonally() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::SouthWest,
+        };
+
+        let expected = Robot {
+            position: Point { x: 0, y: 1 },
+            facing: Orientation::SouthWest,
+        };
+        let actual = robot.advance(Command::Forward);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_turns_on_a_hex_grid() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::East,
+        };
+
+        let expected = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::NorthEast,
+        };
+        let actual = robot.advance_on(Grid::Hex, Command::Left);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn robot_moves_on_a_hex_grid() {
+        let robot = Robot {
+            position: Point { x: 1, y: 2 },
+            facing: Orientation::NorthWest,
+        };
+
+        let expected = Robot {
+            position: Point { x: 1, y: 3 },
+            facing: Orientation::NorthWest,
+        };
+        let actual = robot.advance_on(Grid::Hex, Command::Forward);
+        assert_eq!(actual, Some(expected));
     }
 }
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::geo::location::Point;
+use crate::geo::orientation::Orientation;
+use crate::parser;
+
+// A warning left by a robot lost moving off the grid from a position, travelling towards
+// heading. Scents can be saved after a mission, and used to start the next one.
+#[derive(PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Scent {
+    pub position: Point,
+    pub heading: Orientation,
+}
+
+impl From<(Point, Orientation)> for Scent {
+    fn from((position, heading): (Point, Orientation)) -> Scent {
+        Scent { position, heading }
+    }
+}
+
+impl From<Scent> for (Point, Orientation) {
+    fn from(scent: Scent) -> (Point, Orientation) {
+        (scent.position, scent.heading)
+    }
+}
+
+// Scents are written one per line like a robot, e.g. '3 3 N', or as a JSON array
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    // JSON for files with a .json extension, text for anything else
+    pub fn for_path(path: &Path) -> Format {
+        match path.extension() {
+            Some(extension) if extension == "json" => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+pub fn load(text: &str, format: Format) -> Result<Vec<Scent>, String> {
+    match format {
+        Format::Text => parser::read_scents(text),
+        Format::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+    }
+}
+
+// Writes scents out in a stable order, so saved scent maps can be compared
+pub fn save(scents: &[Scent], format: Format) -> String {
+    let scents = scents.iter().sorted().collect::<Vec<_>>();
+
+    match format {
+        Format::Text => scents.iter().map(|s| format!("{}\n", s)).collect(),
+        Format::Json => serde_json::to_string_pretty(&scents).unwrap() + "\n",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scents() -> Vec<Scent> {
+        vec![
+            Scent {
+                position: Point { x: 3, y: 3 },
+                heading: Orientation::North,
+            },
+            Scent {
+                position: Point { x: -1, y: 0 },
+                heading: Orientation::SouthWest,
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trips_text() {
+        let text = save(&scents(), Format::Text);
+
+        assert_eq!(text, "-1 0 SW\n3 3 N\n");
+        assert_eq!(load(&text, Format::Text).unwrap(), {
+            let mut scents = scents();
+            scents.reverse();
+            scents
+        });
+    }
+
+    #[test]
+    fn round_trips_json() {
+        let json = save(&scents(), Format::Json);
+
+        assert!(json.contains("\"heading\": \"SW\""));
+        assert_eq!(load(&json, Format::Json).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn picks_a_format_by_extension() {
+        assert_eq!(Format::for_path(Path::new("scents.json")), Format::Json);
+        assert_eq!(Format::for_path(Path::new("scents.txt")), Format::Text);
+    }
+}
@@ -0,0 +1,528 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use itertools::{EitherOrBoth, Itertools};
+use serde::{Deserialize, Serialize};
+
+use crate::generator::Settings;
+use crate::geo::grid::Grid;
+use crate::geo::location::Point;
+use crate::geo::orientation::{Compass, Orientation};
+use crate::geo::world::World;
+use crate::mission::{Mission, Obstacles, Outcome};
+use crate::parser::{MissionOutcomes, Plan};
+use crate::print;
+
+// Robots generated for a plan unless asked for a number, and the most that can be asked for
+const ROBOTS: usize = 10;
+const MAX_ROBOTS: usize = 100_000;
+
+// The longest request body handled, and the most commands all the robots in a plan can
+// execute between them, so no one request keeps the service busy for long
+pub const MAX_BODY: usize = 1 << 20;
+const MAX_COMMANDS: usize = 1 << 22;
+
+// A mission's name, if it has one, and the outcomes of its robots
+type Outcomes = (Option<String>, Vec<Outcome>);
+
+// A request to the service, however it arrived
+pub struct Request<'a> {
+    pub method: &'a str,
+    pub url: &'a str, // the path, with any query string
+    pub json: bool,   // whether the body is JSON, rather than text
+    pub body: &'a str,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub json: bool,
+    pub body: String,
+}
+
+impl Response {
+    fn text(body: String) -> Response {
+        Response {
+            status: 200,
+            json: false,
+            body,
+        }
+    }
+
+    fn json<T: Serialize>(body: &T) -> Response {
+        Response {
+            status: 200,
+            json: true,
+            body: serde_json::to_string(body).unwrap() + "\n",
+        }
+    }
+
+    fn error(status: u16, msg: &str) -> Response {
+        Response {
+            status,
+            json: false,
+            body: format!("{}\n", msg.trim_end()),
+        }
+    }
+}
+
+// A plan sent as JSON, rather than the usual text
+#[derive(Deserialize)]
+struct JsonPlan {
+    world: World,
+    robots: Vec<JsonRobot>,
+}
+
+#[derive(Deserialize)]
+struct JsonRobot {
+    position: Point,
+    facing: Orientation,
+    commands: String,
+}
+
+impl JsonPlan {
+    fn text(&self) -> String {
+        let robots = self.robots.iter().map(|r| {
+            let (position, facing) = (r.position, r.facing);
+            format!("{} {}\n{}\n", position, facing, r.commands)
+        });
+
+        format!("{}\n", self.world) + &robots.collect::<String>()
+    }
+}
+
+#[derive(Serialize)]
+struct JsonOutcome {
+    position: Point,
+    facing: Orientation,
+    lost: bool,
+    collided: bool,
+}
+
+impl From<Outcome> for JsonOutcome {
+    fn from(outcome: Outcome) -> JsonOutcome {
+        let (robot, lost, collided) = match outcome {
+            Outcome::Success(robot) => (robot, false, false),
+            Outcome::Lost(robot) => (robot, true, false),
+            Outcome::Collided(robot) => (robot, false, true),
+        };
+
+        JsonOutcome {
+            position: robot.position,
+            facing: robot.facing,
+            lost,
+            collided,
+        }
+    }
+}
+
+// Outcomes to verify against a plan, both sent as text in a JSON body
+#[derive(Deserialize)]
+struct Submission {
+    plan: String,
+    outcomes: String,
+}
+
+// Runs plans, generates them and verifies outcomes over HTTP:
+//
+// - POST /run with a plan, as text or JSON, returns the outcomes in the same format
+// - GET /generate?seed=1&robots=10 returns a plan, taking the same settings as the
+//   generate subcommand as parameters: eight_way, hex, hexagon, lower_left=X,Y and blob
+// - POST /verify?seed=1 with outcomes as text checks them against generated robots,
+//   taking the same settings as /generate
+// - POST /verify with JSON like {"plan": "...", "outcomes": "..."} checks the outcomes
+//   against a plan
+pub struct Service {
+    obstacles: Option<Obstacles>,
+}
+
+impl Service {
+    pub fn new() -> Service {
+        Service { obstacles: None }
+    }
+
+    pub fn with_obstacles(self, obstacles: Obstacles) -> Service {
+        Service {
+            obstacles: Some(obstacles),
+        }
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        let (path, query) = split_url(request.url);
+        if request.body.len() > MAX_BODY {
+            let msg = format!("Requests can be at most {} bytes long", MAX_BODY);
+            return Response::error(413, &msg);
+        }
+
+        let response = match (request.method, path) {
+            ("POST", "/run") if request.json => serde_json::from_str::<JsonPlan>(request.body)
+                .map_err(|e| e.to_string())
+                .and_then(|plan| self.run(&plan.text()))
+                .map(|missions| {
+                    let outcomes: Vec<_> = missions
+                        .into_iter()
+                        .flat_map(|(_, outcomes)| outcomes)
+                        .map(JsonOutcome::from)
+                        .collect();
+                    Response::json(&outcomes)
+                }),
+            ("POST", "/run") => self.run(request.body).map(|missions| {
+                let mut text = Vec::new();
+                for (name, outcomes) in missions {
+                    // labelled like the command line does, if started by a '===' line
+                    print::label(&mut text, name.as_deref()).unwrap();
+                    for outcome in outcomes {
+                        print::outcome(&mut text, &outcome).unwrap();
+                    }
+                }
+                Response::text(String::from_utf8(text).unwrap())
+            }),
+            ("GET", "/generate") => self.generate(&query),
+            ("POST", "/verify") if request.json => serde_json::from_str::<Submission>(request.body)
+                .map_err(|e| e.to_string())
+                .and_then(|submission| {
+                    let missions = self.run(&submission.plan)?;
+                    let expected = missions.into_iter().flat_map(|(_, outcomes)| outcomes);
+                    Ok(self.verify(expected, read_outcomes(&submission.outcomes)?))
+                }),
+            ("POST", "/verify") => settings(&query).and_then(|settings| {
+                let actual = read_outcomes(request.body)?;
+                // generators go on forever, so only as many robots as outcomes are checked
+                let n = actual.len();
+                let generator = settings.generator()?.mission();
+                Ok(match self.obstacles {
+                    Some(obstacles) => {
+                        self.verify(generator.with_obstacles(obstacles).take(n), actual)
+                    }
+                    None => self.verify(generator.take(n), actual),
+                })
+            }),
+            (_, "/run") | (_, "/generate") | (_, "/verify") => {
+                return Response::error(405, "Method not allowed")
+            }
+            _ => return Response::error(404, "Not found"),
+        };
+
+        response.unwrap_or_else(|msg| Response::error(400, &msg))
+    }
+
+    // Runs a plan on a grid, which may hold several missions, giving each mission's name
+    // and outcomes
+    fn run(&self, plan: &str) -> Result<Vec<Outcomes>, String> {
+        let mut input = Cursor::new(plan);
+        let mut next = Some(Plan::read(&mut input));
+        let mut missions = Vec::new();
+        let mut commands = 0; // across every mission
+
+        while let Some(plan) = next.take() {
+            let mut plan = match plan? {
+                Plan::Surface(plan) => plan,
+                Plan::Flight(_) => return Err("Only plans for robots on a grid can be run".into()),
+            };
+
+            let world = plan.world.clone();
+            let robots = (&mut plan).map(|robot| {
+                let (robot, given) = robot?;
+                commands += given.len();
+                if commands > MAX_COMMANDS {
+                    return Err(format!("Plans can have at most {} commands", MAX_COMMANDS));
+                }
+                Ok((robot, given))
+            });
+
+            let mut mission = Mission::in_world(world, robots);
+            if let Some(obstacles) = self.obstacles {
+                mission = mission.with_obstacles(obstacles);
+            }
+            let outcomes = mission.collect::<Result<_, _>>()?;
+
+            missions.push((plan.name.clone(), outcomes));
+            next = plan.next_plan();
+        }
+
+        Ok(missions)
+    }
+
+    fn generate(&self, query: &HashMap<String, String>) -> Result<Response, String> {
+        let settings = settings(query)?;
+        let robots = match query.get("robots") {
+            Some(robots) => robots.parse().map_err(|_| "Invalid number of robots")?,
+            None => ROBOTS,
+        };
+        if robots > MAX_ROBOTS {
+            return Err(format!("At most {} robots can be generated", MAX_ROBOTS));
+        }
+
+        let generator = settings.generator()?;
+        let mut plan = Vec::new();
+        print::plan(&mut plan, &generator.world(), generator.take(robots)).unwrap();
+
+        Ok(Response::text(String::from_utf8(plan).unwrap()))
+    }
+
+    // Checks outcomes against the expected ones, counting any missing or extra outcomes as
+    // wrong
+    fn verify<E: Iterator<Item = Outcome>>(&self, expected: E, actual: Vec<Outcome>) -> Response {
+        let mut checks = Vec::new();
+        let (mut correct, mut total) = (0, 0);
+
+        for pair in expected.zip_longest(actual) {
+            total += 1;
+            match pair {
+                EitherOrBoth::Both(expected, actual) => {
+                    correct += print::check(&mut checks, expected, actual).unwrap() as usize;
+                }
+                EitherOrBoth::Left(expected) => print::missing(&mut checks, expected).unwrap(),
+                EitherOrBoth::Right(actual) => print::unexpected(&mut checks, actual).unwrap(),
+            }
+        }
+        print::summary(&mut checks, (correct, total)).unwrap();
+
+        Response::text(String::from_utf8(checks).unwrap())
+    }
+}
+
+impl Default for Service {
+    fn default() -> Service {
+        Service::new()
+    }
+}
+
+// Reads every outcome submitted for verifying, failing at the first which can't be read
+fn read_outcomes(text: &str) -> Result<Vec<Outcome>, String> {
+    let mut input = Cursor::new(text);
+    MissionOutcomes::read(&mut input).collect()
+}
+
+// Generator settings from query parameters, named like the generate subcommand's options
+fn settings(query: &HashMap<String, String>) -> Result<Settings, String> {
+    let flag = |name: &str| query.get(name).is_some_and(|v| v != "false");
+
+    let seed = match query.get("seed") {
+        Some(seed) => seed.parse().map_err(|_| "Invalid seed")?,
+        None => return Err("A seed is needed".into()),
+    };
+    let lower_left = match query.get("lower_left") {
+        Some(point) => Some(parse_point(point)?),
+        None => None,
+    };
+
+    let settings = Settings {
+        compass: if flag("eight_way") {
+            Compass::Eight
+        } else {
+            Compass::Four
+        },
+        grid: if flag("hex") { Grid::Hex } else { Grid::Square },
+        lower_left,
+        blob: flag("blob"),
+        hexagon: flag("hexagon"),
+        ..Settings::new(seed)
+    };
+
+    // refused like the command line and Python bindings refuse them
+    settings.check()?;
+    Ok(settings)
+}
+
+fn parse_point(text: &str) -> Result<Point, String> {
+    let mut coordinates = text.split(',').map(|c| c.trim().parse());
+
+    match (coordinates.next(), coordinates.next(), coordinates.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Ok(Point { x, y }),
+        _ => Err(format!("Expected a point written as X,Y, got '{}'", text)),
+    }
+}
+
+// Splits a URL into its path and query parameters
+fn split_url(url: &str) -> (&str, HashMap<String, String>) {
+    let (path, query) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None => (url, ""),
+    };
+
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(i) => (decode(&pair[..i]), decode(&pair[i + 1..])),
+            None => (decode(pair), String::new()),
+        })
+        .collect();
+
+    (path, query)
+}
+
+// Decodes a percent-encoded query string component
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(method: &'a str, url: &'a str, json: bool, body: &'a str) -> Response {
+        let response = Service::new().handle(&Request {
+            method,
+            url,
+            json,
+            body,
+        });
+
+        Response {
+            body: print::uncoloured(&response.body),
+            ..response
+        }
+    }
+
+    #[test]
+    fn runs_plans() {
+        let text = request(
+            "POST",
+            "/run",
+            false,
+            "5 3\n1 1 E\nRFRFRFRF\n\n3 2 N\nFRRFLLFFRRFLL\n",
+        );
+        assert_eq!(text.body, "1 1 E\n3 3 N LOST\n");
+
+        let text = request("POST", "/run", false, "5 3\n1 1 E\nF\n===\n5 3\n1 1 E\nL\n");
+        assert_eq!(text.body, "2 1 E\n=== Mission 2\n1 1 N\n");
+
+        let text = request(
+            "POST",
+            "/run",
+            false,
+            "===\n5 3\n1 1 E\nF\n===\n5 3\n1 1 E\nL\n",
+        );
+        assert_eq!(text.body, "=== Mission 1\n2 1 E\n=== Mission 2\n1 1 N\n");
+
+        let json = r#"{
+            "world": {"grid": "Square", "shape": {"Rectangle": [{"x": 0, "y": 0}, {"x": 5, "y": 3}]}},
+            "robots": [{"position": {"x": 3, "y": 2}, "facing": "N", "commands": "FRRFLLFFRRFLL"}]
+        }"#;
+        let json = request("POST", "/run", true, json);
+        assert!(json.json);
+        assert!(json.body.contains(r#""lost":true"#));
+
+        assert_eq!(request("POST", "/run", false, "5 3\n1 1 Q\n").status, 400);
+    }
+
+    #[test]
+    fn verifies_generated_outcomes() {
+        let plan = request("GET", "/generate?seed=7&robots=3&eight_way", false, "");
+        let outcomes = request("POST", "/run", false, &plan.body);
+
+        let checks = request(
+            "POST",
+            "/verify?seed=7&eight_way=true",
+            false,
+            &outcomes.body,
+        );
+        assert!(checks.body.ends_with("3 of 3 outcomes correct\n"));
+
+        let submission = serde_json::json!({"plan": plan.body, "outcomes": "0 0 N\n"});
+        let checks = request("POST", "/verify", true, &submission.to_string());
+        assert!(checks.body.ends_with("0 of 3 outcomes correct\n"));
+    }
+
+    #[test]
+    fn verifies_every_outcome_of_a_plan() {
+        let plan = "5 3\n1 1 E\nRFRFRFRF\n\n3 2 N\nFRRFLLFFRRFLL\n\n0 3 W\nLLFFFLFLFL\n";
+        let verify = |outcomes: &str| {
+            let submission = serde_json::json!({"plan": plan, "outcomes": outcomes});
+            request("POST", "/verify", true, &submission.to_string())
+        };
+
+        let missing = verify("1 1 E\n");
+        assert!(missing
+            .body
+            .contains("⨯ Expected: 3 3 N LOST, got nothing\n"));
+        assert!(missing.body.ends_with("1 of 3 outcomes correct\n"));
+
+        let extra = verify("1 1 E\n3 3 N LOST\n2 3 S\n0 0 N\n");
+        assert!(extra.body.contains("⨯ Unexpected: 0 0 N\n"));
+        assert!(extra.body.ends_with("3 of 4 outcomes correct\n"));
+
+        let malformed = verify("1 1 E\n3 3 Q\n");
+        assert_eq!(malformed.status, 400);
+        assert_eq!(
+            request("POST", "/verify?seed=7", false, "1 1\n").status,
+            400
+        );
+    }
+
+    #[test]
+    fn refuses_requests_too_large_to_handle() {
+        let body = format!("5 3\n1 1 E\n{}\n", "F".repeat(MAX_BODY));
+        assert_eq!(request("POST", "/run", false, &body).status, 413);
+
+        // each robot's commands are few enough, but not all of them together
+        let plan = format!("5 3\n{}", "1 1 E\n(LR)500000\n\n".repeat(5));
+        let text = request("POST", "/run", false, &plan);
+        assert_eq!(text.status, 400);
+        assert!(text.body.contains("at most"));
+        assert_eq!(
+            request("POST", "/run", false, "5 3\n1 1 E\n40000000000F\n").status,
+            400
+        );
+
+        // nor with the commands spread over several missions
+        let plan = ["5 3\n1 1 E\n(LR)500000\n"; 5].join("===\n");
+        let text = request("POST", "/run", false, &plan);
+        assert_eq!(text.status, 400);
+        assert!(text.body.contains("at most"));
+    }
+
+    #[test]
+    fn refuses_settings_plans_cannot_describe() {
+        let blob = request("GET", "/generate?seed=1&hex&blob", false, "");
+        let hexagon = request("GET", "/generate?seed=1&hexagon", false, "");
+
+        assert_eq!(blob.status, 400);
+        assert_eq!(blob.body, "Hex grids can't be eight way or blobs\n");
+        assert_eq!(hexagon.status, 400);
+        assert_eq!(
+            request("GET", "/generate?seed=1&hex&hexagon", false, "").status,
+            200
+        );
+    }
+
+    #[test]
+    fn reads_query_parameters() {
+        let (path, query) = split_url("/generate?seed=3&lower_left=-2%2C5&blob");
+
+        assert_eq!(path, "/generate");
+        assert_eq!(query["lower_left"], "-2,5");
+        assert_eq!(
+            settings(&query).unwrap().lower_left,
+            Some(Point { x: -2, y: 5 })
+        );
+        assert!(settings(&query).unwrap().blob);
+    }
+}
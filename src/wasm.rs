@@ -0,0 +1,227 @@
+use std::io::Cursor;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::generator::Settings;
+use crate::geo::grid::Grid;
+use crate::geo::location::{Coord, Point};
+use crate::geo::orientation::{Compass, Orientation};
+use crate::geo::world::World;
+use crate::mission::{Mission, Outcome, Step};
+use crate::parser::Plan;
+use crate::print;
+use crate::robot::{Command, Robot};
+
+// The JavaScript API of the playground in web/, built with
+//
+//     wasm-pack build --target web --out-dir web/pkg -- --features wasm
+//
+// Results are handed over as JSON, which the page parses.
+
+// Worlds with more cells than this are run, but their cells aren't listed for drawing
+const MAX_CELLS: usize = 1 << 16;
+
+#[derive(Serialize)]
+struct MissionTrace {
+    name: Option<String>,
+    world: WorldView,
+    robots: Vec<RobotTrace>,
+}
+
+#[derive(Serialize)]
+struct WorldView {
+    hex: bool,
+    lower_left: Point,
+    upper_right: Point,
+    cells: Vec<Point>, // within the bounds, which leaves out any holes
+}
+
+#[derive(Serialize)]
+struct RobotTrace {
+    start: Pose,
+    commands: String,
+    steps: Vec<StepTrace>,
+    end: Pose,
+    lost: bool,
+    collided: bool,
+    outcome: String, // as the command line prints it
+}
+
+#[derive(Serialize)]
+struct Pose {
+    x: Coord,
+    y: Coord,
+    facing: Orientation,
+}
+
+#[derive(Serialize)]
+struct StepTrace {
+    #[serde(flatten)]
+    pose: Pose,
+    kind: &'static str,
+}
+
+impl From<Robot> for Pose {
+    fn from(robot: Robot) -> Pose {
+        Pose {
+            x: robot.position.x,
+            y: robot.position.y,
+            facing: robot.facing,
+        }
+    }
+}
+
+impl From<Step> for StepTrace {
+    fn from(step: Step) -> StepTrace {
+        let kind = match step {
+            Step::Moved(_) => "moved",
+            Step::Scented(_) => "scented",
+            Step::Blocked(_) => "blocked",
+            Step::Collided(_) => "collided",
+            Step::Lost(_) => "lost",
+        };
+
+        StepTrace {
+            pose: step.robot().into(),
+            kind,
+        }
+    }
+}
+
+impl From<&World> for WorldView {
+    fn from(world: &World) -> WorldView {
+        let (lower_left, upper_right) = world.bounds().unwrap_or((World::ORIGIN, World::ORIGIN));
+        let (columns, rows) = (
+            upper_right.x - lower_left.x + 1,
+            upper_right.y - lower_left.y + 1,
+        );
+
+        let mut cells = Vec::new();
+        if (columns as i128) * (rows as i128) <= MAX_CELLS as i128 {
+            for row in lower_left.y..=upper_right.y {
+                for column in lower_left.x..=upper_right.x {
+                    let cell = world.position(column, row);
+                    if world.contains(cell) {
+                        cells.push(cell);
+                    }
+                }
+            }
+        }
+
+        WorldView {
+            hex: world.grid == Grid::Hex,
+            lower_left,
+            upper_right,
+            cells,
+        }
+    }
+}
+
+// Runs every mission in a plan, tracing each robot step by step
+fn trace(plan: &str) -> Result<Vec<MissionTrace>, String> {
+    let mut input = Cursor::new(plan);
+    let mut next = Some(Plan::read(&mut input));
+    let mut missions = Vec::new();
+
+    while let Some(plan) = next.take() {
+        let mut plan = match plan? {
+            Plan::Surface(plan) => plan,
+            Plan::Flight(_) => return Err("Only plans for robots on a grid can be run".into()),
+        };
+        let world = plan.world.clone();
+        let fleet = (&mut plan).collect::<Result<Vec<_>, _>>()?;
+
+        let mut mission: Mission<_, (Robot, Vec<Command>)> =
+            Mission::in_world(world.clone(), Vec::new());
+        let robots = fleet
+            .into_iter()
+            .map(|(robot, commands)| {
+                let mut steps = Vec::new();
+                let outcome = mission.dispatch_with(robot, &commands, |step| {
+                    steps.push(StepTrace::from(step));
+                });
+                let (end, lost, collided) = match outcome {
+                    Outcome::Success(end) => (end, false, false),
+                    Outcome::Lost(end) => (end, true, false),
+                    Outcome::Collided(end) => (end, false, true),
+                };
+
+                RobotTrace {
+                    start: robot.into(),
+                    commands: commands.iter().map(ToString::to_string).collect(),
+                    steps,
+                    end: end.into(),
+                    lost,
+                    collided,
+                    outcome: outcome.to_string(),
+                }
+            })
+            .collect();
+
+        missions.push(MissionTrace {
+            name: plan.name.clone(),
+            world: WorldView::from(&world),
+            robots,
+        });
+        next = plan.next_plan();
+    }
+
+    Ok(missions)
+}
+
+// Runs a plan, returning a JSON list of its missions, with every step each robot took
+#[wasm_bindgen]
+pub fn run(plan: &str) -> Result<String, JsValue> {
+    trace(plan)
+        .map(|missions| serde_json::to_string(&missions).unwrap())
+        .map_err(|msg| JsValue::from_str(&msg))
+}
+
+// Generates a plan just like the generate subcommand does with the same seed
+#[wasm_bindgen]
+pub fn generate(
+    seed: u64,
+    robots: usize,
+    eight_way: bool,
+    hex: bool,
+    hexagon: bool,
+) -> Result<String, JsValue> {
+    let settings = Settings {
+        compass: if eight_way {
+            Compass::Eight
+        } else {
+            Compass::Four
+        },
+        grid: if hex { Grid::Hex } else { Grid::Square },
+        hexagon,
+        ..Settings::new(seed)
+    };
+
+    let generator = settings
+        .generator()
+        .map_err(|msg| JsValue::from_str(&msg))?;
+    let mut plan = Vec::new();
+    print::plan(&mut plan, &generator.world(), generator.take(robots)).unwrap();
+
+    Ok(String::from_utf8(plan).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_every_step() {
+        let missions =
+            trace("5 3\n1 1 E\nRFRFRFRF\n\n3 2 N\nFRRFLLFFRRFLL\n\n0 3 W\nLLFFFLFLFL\n").unwrap();
+        let robots = &missions[0].robots;
+
+        assert_eq!(missions[0].world.cells.len(), 24);
+        assert_eq!(robots[0].steps.len(), 8);
+        assert!(robots[1].lost);
+        assert_eq!(robots[1].steps.last().unwrap().kind, "lost");
+        assert!(robots[2].steps.iter().any(|s| s.kind == "scented"));
+        assert_eq!(robots[2].outcome, "2 3 S");
+    }
+}
@@ -0,0 +1,52 @@
+/* Runs the sample plan through the C API. Build the library first, then run the test:
+ *
+ *     cargo build --features capi && make -C tests/c
+ */
+
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "martian_robots.h"
+
+static MrOutcome dispatch(MrMission *mission, int64_t x, int64_t y, const char *facing,
+                          const char *commands) {
+  MrOutcome outcome;
+  MrStatus status = mr_mission_dispatch(mission, x, y, facing, commands, &outcome);
+  assert(status == MR_STATUS_OK);
+  return outcome;
+}
+
+int main(void) {
+  MrMission *mission = mr_mission_new(5, 3);
+  assert(mission != NULL);
+
+  MrOutcome first = dispatch(mission, 1, 1, "E", "RFRFRFRF");
+  assert(first.x == 1 && first.y == 1 && strcmp(first.facing, "E") == 0);
+  assert(first.result == MR_RESULT_SUCCESS);
+
+  MrOutcome second = dispatch(mission, 3, 2, "N", "FRRFLLFFRRFLL");
+  assert(second.x == 3 && second.y == 3 && strcmp(second.facing, "N") == 0);
+  assert(second.result == MR_RESULT_LOST);
+
+  /* the scent left by the second robot saves the third */
+  MrOutcome third = dispatch(mission, 0, 3, "W", "LLFFFLFLFL");
+  assert(third.x == 2 && third.y == 3 && strcmp(third.facing, "S") == 0);
+  assert(third.result == MR_RESULT_SUCCESS);
+
+  MrScent scents[4];
+  size_t count = mr_mission_scents(mission, scents, 4);
+  assert(count == 1);
+  assert(scents[0].x == 3 && scents[0].y == 3 && strcmp(scents[0].heading, "N") == 0);
+
+  MrOutcome outcome;
+  assert(mr_mission_dispatch(mission, 1, 1, "Q", "F", &outcome) == MR_STATUS_INVALID_FACING);
+  assert(mr_mission_dispatch(mission, 1, 1, "N", "FX", &outcome) == MR_STATUS_INVALID_COMMAND);
+  assert(mr_mission_dispatch(NULL, 1, 1, "N", "F", &outcome) == MR_STATUS_NULL_POINTER);
+
+  mr_mission_free(mission);
+  mr_mission_free(NULL);
+
+  printf("C API tests passed\n");
+  return 0;
+}
@@ -0,0 +1,42 @@
+# Checks the Python module gives the same results as the command line. Build it into the
+# current environment with `maturin develop`, then run `pytest tests/python`.
+
+import martian_robots as mr
+
+SAMPLE = """5 3
+1 1 E
+RFRFRFRF
+
+3 2 N
+FRRFLLFFRRFLL
+
+0 3 W
+LLFFFLFLFL
+"""
+
+
+def test_runs_the_sample():
+    outcomes = mr.run(SAMPLE)
+
+    assert [str(o) for o in outcomes] == ["1 1 E", "3 3 N LOST", "2 3 S"]
+    assert outcomes[1].lost
+    assert outcomes == mr.parse_outcomes("1 1 E\n3 3 N LOST\n2 3 S\n")
+
+
+def test_traces_robots_and_keeps_scents():
+    plan = mr.parse_plan(SAMPLE)[0]
+    mission = mr.Mission(plan.world)
+    traces = [mission.trace(robot) for robot in plan.robots]
+
+    assert len(traces[0].steps) == 8
+    assert traces[1].steps[-1].kind == "lost"
+    assert any(step.kind == "scented" for step in traces[2].steps)
+    assert mission.scents == [(3, 3, "N")]
+
+
+def test_expects_what_running_a_generated_plan_gives():
+    generator = mr.Generator(7, eight_way=True)
+
+    assert generator.seed == 7
+    assert mr.run(generator.plan(50)) == generator.expected(50)
+    assert generator.plan(5).startswith(generator.world + "\n")
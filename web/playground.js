@@ -0,0 +1,154 @@
+// Runs plans in the browser with the wasm build of the simulator, replaying every robot
+// step by step on a canvas. Build the bindings into web/pkg first:
+//
+//     wasm-pack build --target web --out-dir web/pkg -- --features wasm
+//
+// and serve this directory, e.g. with `python3 -m http.server -d web`.
+
+import init, { run, generate } from "./pkg/martian_robots.js";
+
+const $ = (id) => document.getElementById(id);
+
+const ARROWS = { N: [0, 1], E: [1, 0], S: [0, -1], W: [-1, 0], NE: [1, 1], SE: [1, -1], SW: [-1, -1], NW: [-1, 1] };
+const COLOURS = { moved: "#36c", scented: "#c90", blocked: "#999", collided: "#c60", lost: "#c00" };
+
+let missions = [];
+
+// Every pose a mission's robots take, one after another, as the mission runs them
+function timeline(mission) {
+  return mission.robots.flatMap((robot, index) => [
+    { robot: index, pose: robot.start, kind: "start" },
+    ...robot.steps.map((step) => ({ robot: index, pose: step, kind: step.kind })),
+  ]);
+}
+
+// Where a cell is drawn, in cells from the lower left; hex rows are offset by half a cell
+function place(world, x, y) {
+  return world.hex ? [x - y / 2, y] : [x, y];
+}
+
+function draw() {
+  const mission = missions[$("mission").value];
+  if (!mission) return;
+
+  const canvas = $("grid");
+  const ctx = canvas.getContext("2d");
+  const world = mission.world;
+  const events = timeline(mission);
+  const now = Math.min(Number($("step").value), events.length - 1);
+
+  const corners = [world.lower_left, world.upper_right];
+  const spots = [...world.cells, ...corners].map((c) => place(world, c.x, c.y));
+  const left = Math.min(...spots.map(([x]) => x));
+  const right = Math.max(...spots.map(([x]) => x));
+  const [bottom, top] = [world.lower_left.y, world.upper_right.y];
+  const size = Math.max(4, Math.min(40, 580 / (right - left + 1), 380 / (top - bottom + 1)));
+
+  canvas.width = (right - left + 1) * size + 20;
+  canvas.height = (top - bottom + 1) * size + 20;
+  const centre = (x, y) => {
+    const [px, py] = place(world, x, y);
+    return [10 + (px - left + 0.5) * size, canvas.height - 10 - (py - bottom + 0.5) * size];
+  };
+
+  ctx.strokeStyle = "#ddd";
+  for (const cell of world.cells) {
+    const [cx, cy] = centre(cell.x, cell.y);
+    ctx.strokeRect(cx - size / 2, cy - size / 2, size, size);
+  }
+
+  // scents are left in the cells robots were lost from
+  for (const event of events.slice(0, now + 1)) {
+    if (event.kind !== "lost") continue;
+    const [cx, cy] = centre(event.pose.x, event.pose.y);
+    ctx.fillStyle = "rgba(200, 0, 0, 0.2)";
+    ctx.fillRect(cx - size / 2, cy - size / 2, size, size);
+  }
+
+  // paths of every robot so far, with the current one drawn boldest
+  const current = events[now];
+  mission.robots.forEach((robot, index) => {
+    if (index > current.robot) return;
+    const poses = [robot.start, ...robot.steps];
+    const first = events.findIndex((e) => e.robot === index);
+    const shown = index < current.robot ? poses : poses.slice(0, now - first + 1);
+
+    ctx.strokeStyle = index === current.robot ? "#36c" : "rgba(51, 102, 204, 0.25)";
+    ctx.lineWidth = index === current.robot ? 3 : 1;
+    ctx.beginPath();
+    shown.forEach((pose, i) => {
+      const [cx, cy] = centre(pose.x, pose.y);
+      i === 0 ? ctx.moveTo(cx, cy) : ctx.lineTo(cx, cy);
+    });
+    ctx.stroke();
+  });
+
+  const [cx, cy] = centre(current.pose.x, current.pose.y);
+  const [dx, dy] = ARROWS[current.pose.facing];
+  ctx.fillStyle = COLOURS[current.kind] || "#36c";
+  ctx.beginPath();
+  ctx.arc(cx, cy, size / 4, 0, 2 * Math.PI);
+  ctx.fill();
+  ctx.strokeStyle = ctx.fillStyle;
+  ctx.beginPath();
+  ctx.moveTo(cx, cy);
+  ctx.lineTo(cx + (dx * size) / 2, cy - (dy * size) / 2);
+  ctx.stroke();
+  ctx.lineWidth = 1;
+
+  $("tick").textContent = `robot ${current.robot + 1} of ${mission.robots.length}: ${current.kind}`;
+  $("step").max = events.length - 1;
+  $("outcomes").innerHTML = mission.robots
+    .map((r, i) => {
+      const text = i <= current.robot ? r.outcome : "";
+      const style = r.lost ? "lost" : r.collided ? "collided" : "";
+      return i === current.robot ? `<b class="${style}">${text}</b>` : `<span class="${style}">${text}</span>`;
+    })
+    .join("\n");
+}
+
+function runPlan() {
+  $("error").textContent = "";
+  try {
+    missions = JSON.parse(run($("plan").value));
+  } catch (e) {
+    $("error").textContent = e;
+    missions = [];
+  }
+
+  $("mission").innerHTML = missions
+    .map((m, i) => `<option value="${i}">${m.name || `Mission ${i + 1}`}</option>`)
+    .join("");
+  $("mission").hidden = missions.length < 2;
+  $("step").value = 0;
+  draw();
+}
+
+await init();
+
+$("run").onclick = runPlan;
+$("generate").onclick = () => {
+  const seed = BigInt($("seed").value || 0);
+  try {
+    $("plan").value = generate(seed, Number($("robots").value), $("eight-way").checked, $("hex").checked, $("hexagon").checked);
+  } catch (e) {
+    $("error").textContent = e;
+    return;
+  }
+  runPlan();
+};
+$("mission").onchange = () => {
+  $("step").value = 0;
+  draw();
+};
+$("step").oninput = draw;
+$("back").onclick = () => {
+  $("step").value = Number($("step").value) - 1;
+  draw();
+};
+$("forward").onclick = () => {
+  $("step").value = Number($("step").value) + 1;
+  draw();
+};
+
+runPlan();